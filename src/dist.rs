@@ -1,10 +1,20 @@
-use crate::data::CUM;
+use crate::{
+    prior::Prior,
+    sample::{Halton, TruncMixture},
+    scale::Scale,
+    timeline::{prob_from_portion, Interp},
+    util::{isotonic, moving_average},
+    CoordFn,
+};
 use itertools_num::linspace;
 use lazy_static::*;
 use plotters::prelude::*;
 use rand::prelude::*;
-use rand_distr::{Beta, Exp, Uniform};
 use rand_xorshift::XorShiftRng;
+use std::cmp::Ordering;
+
+/// Spacing of the points a freehand stroke is resampled to.
+const STROKE_SPACING: f32 = 0.02;
 
 /// Bounds points on distribution plot.
 fn bound(x: f32) -> f32 {
@@ -17,100 +27,306 @@ fn bound(x: f32) -> f32 {
     }
 }
 
-/// Different types of provided distributions
-pub enum DistMode {
-    /// Mixture of Paretos where the `q` parameter is sampled uniformly from [0.001, 1)
-    Uniform,
-    /// A single Pareto where the `q` parameter is provided by the user.
-    Q(f32),
-    /// Mixture of Paretos where the `q` parameter is sampled from a Beta.
-    Beta(f32, f32),
+lazy_static! {
+    /// Grid of portions the progress distribution is evaluated at.
+    static ref PORTIONS: Vec<f32> = linspace(0.005, 0.999, 500).collect();
 }
 
-/// Generate a Pareto or mixture of Paretos.
+/// The grid of portions the progress distribution is evaluated at, along with the total number of
+/// insights required for each portion to have been discovered so far.
 ///
 /// # Arguments
 ///
-/// - `min: u32` - Plausible minimum number of insights to achieve AGI.
-/// - `n_samps: usize` - Number of samples to take from the distribution.
-/// - `mode: DistMode` - Which distribution to sample from.
-pub fn pareto(min: u32, n_samps: usize, mode: DistMode) -> Vec<(f32, f32)> {
-    // static allocation, prevent repeated work
-    lazy_static! {
-        static ref REQS: Vec<(f32, f32)> = linspace(0.005, 0.999, 500)
-            .map(|x| {
-                let max_cnt = CUM.last().unwrap().1 as f32;
-                (x, max_cnt / x)
-            })
-            .collect();
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+fn reqs(insights_cnt: f32) -> Vec<(f32, f32)> {
+    PORTIONS.iter().map(|&p| (p, insights_cnt / p)).collect()
+}
+
+/// Adds the points at zero and one to a progress distribution evaluated on `PORTIONS`, so that it
+/// covers every portion. These are exact: none of the required insights have been discovered only
+/// if infinitely many are required, which proper priors rule out, and all of them have been
+/// discovered if no more than the insights discovered so far are required, which conditioning
+/// rules out.
+fn with_ends(pts: impl IntoIterator<Item = (f32, f32)>) -> Vec<(f32, f32)> {
+    std::iter::once((0., 0.))
+        .chain(pts)
+        .chain(std::iter::once((1., 1.)))
+        .collect()
+}
+
+/// The probability that at least `reqs` insights are required under a mixture of priors, before
+/// conditioning on the insights discovered so far.
+fn mixture_survival(components: &[(f32, Prior)], reqs: f64) -> f64 {
+    let total: f64 = components.iter().map(|&(w, _)| w as f64).sum();
+
+    components
+        .iter()
+        .map(|&(w, prior)| w as f64 * prior.survival(reqs))
+        .sum::<f64>()
+        / total
+}
+
+/// The probability that a sample from a mixture of priors exceeds the number of insights discovered
+/// so far, i.e., the acceptance rate of naive rejection sampling.
+///
+/// # Arguments
+///
+/// - `components: &[(f32, Prior)]` - The weight and prior of each component.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub fn acceptance(components: &[(f32, Prior)], insights_cnt: f32) -> f32 {
+    mixture_survival(components, insights_cnt as f64) as f32
+}
+
+/// Computes the progress distribution implied by a mixture of priors exactly, rather than by
+/// sampling.
+///
+/// Returns `None` if the parameters of any component are invalid, if any weight isn't positive, or
+/// if the mixture rules out exceeding the number of insights discovered so far.
+///
+/// # Arguments
+///
+/// - `components: &[(f32, Prior)]` - The weight and prior of each component.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub fn exact_prior(components: &[(f32, Prior)], insights_cnt: f32) -> Option<Vec<(f32, f32)>> {
+    if components
+        .iter()
+        .any(|&(w, prior)| w <= 0. || !prior.is_valid())
+    {
+        return None;
     }
 
-    // Xor shift random number generator (https://en.wikipedia.org/wiki/Xorshift) with known seed,
-    // output should be deterministic up to floating point error.
-    let mut rng = XorShiftRng::seed_from_u64(1);
-
-    // parameterized Pareto.
-    let lambda = |q: f32| -> f64 { -(1. - q).ln() as f64 };
-
-    // pre-allocate vector
-    let mut samples = Vec::with_capacity(n_samps);
-
-    match mode {
-        DistMode::Uniform => {
-            let mut uni_rng = XorShiftRng::seed_from_u64(1);
-            let dist = Uniform::from(0.001f32..1f32);
-
-            for _ in 0..n_samps {
-                samples.push(
-                    dist.sample_iter(&mut uni_rng)
-                        .map(|q| {
-                            let dist = Exp::new(lambda(q)).unwrap();
-                            dist.sample(&mut rng)
-                        })
-                        .map(|x| x.min(800.0) as f32) // prevent overflow
-                        .map(|x| min as f32 * 2f32.powf(x))
-                        .find(|&x| x > (CUM.last().unwrap().1 as f32)) // This is effectively a Bayesian update
-                        .unwrap(),
-                );
-            }
+    // This is effectively a Bayesian update on the insights discovered so far.
+    let evidence = mixture_survival(components, insights_cnt as f64);
+
+    if evidence.is_nan() || evidence <= 0. {
+        return None;
+    }
+
+    let pts = reqs(insights_cnt).into_iter().map(|(p, r)| {
+        let prob = mixture_survival(components, r as f64) / evidence;
+        (p, prob as f32)
+    });
+
+    Some(with_ends(pts))
+}
+
+/// Derives an independent random number stream from a seed.
+///
+/// Xor shift random number generator (https://en.wikipedia.org/wiki/Xorshift) with known seed,
+/// output should be deterministic up to floating point error. Each stream perturbs the seed by a
+/// multiple of the golden ratio, and `seed_from_u64` scrambles the result, so streams derived from
+/// the same seed don't overlap.
+///
+/// # Arguments
+///
+/// - `seed: u64` - The seed shared by all streams.
+/// - `stream: u64` - Which stream to derive.
+fn rng_stream(seed: u64, stream: u64) -> XorShiftRng {
+    XorShiftRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// The largest number of samples to take when sampling until a target precision is reached.
+const MAX_SAMPS: usize = 10_000_000;
+
+/// Where the randomness used for sampling comes from.
+#[derive(Clone, Copy)]
+pub enum SampleMethod {
+    /// Pseudo-random numbers.
+    Random,
+    /// A randomly shifted Halton sequence, which covers the space of draws more evenly and so
+    /// gives smoother progress distributions for the same number of samples.
+    Quasi,
+}
+
+/// How to sample from a prior.
+#[derive(Clone, Copy)]
+pub struct Sampler {
+    /// Number of samples to take from the distribution, or to take in each batch if sampling until
+    /// a target precision is reached.
+    pub n_samps: usize,
+    /// Seed for the random number generators.
+    pub seed: u64,
+    /// If set, keep sampling until the standard error of every point is at most this.
+    pub target_se: Option<f32>,
+    /// Where the randomness comes from.
+    pub method: SampleMethod,
+}
+
+/// A streaming tally of samples from a prior. Rather than storing the samples, this counts how many
+/// of them require at least as many insights as each point of `reqs`, so that the memory needed
+/// doesn't grow with the number of samples.
+struct Tally {
+    /// The grid of portions and the number of insights required for each, from `reqs`.
+    reqs: Vec<(f32, f32)>,
+    /// `hist[k]` counts the samples for which `k` is the first index into `reqs` whose number of
+    /// required insights doesn't exceed the sample.
+    hist: Vec<usize>,
+    /// The total number of samples.
+    n: usize,
+}
+
+impl Tally {
+    fn new(insights_cnt: f32) -> Tally {
+        let reqs = reqs(insights_cnt);
+        Tally {
+            hist: vec![0; reqs.len() + 1],
+            reqs,
+            n: 0,
         }
-        DistMode::Q(q) => {
-            let dist = Exp::new(lambda(q)).unwrap();
-
-            for _ in 0..n_samps {
-                samples.push(
-                    dist.sample_iter(&mut rng)
-                        .map(|x| x.min(800.0) as f32) // prevent overflow
-                        .map(|x| min as f32 * 2f32.powf(x))
-                        .find(|&x| x > (CUM.last().unwrap().1 as f32))
-                        .unwrap(),
-                );
+    }
+
+    /// Adds a sample to the tally.
+    fn add(&mut self, x: f32) {
+        // The number of required insights decreases along `reqs`, so this finds the first point
+        // that the sample requires at least as many insights as.
+        let k = match self.reqs.binary_search_by(|&(_, r)| {
+            if r > x {
+                Ordering::Less
+            } else {
+                Ordering::Greater
             }
+        }) {
+            Ok(k) | Err(k) => k,
+        };
+
+        self.hist[k] += 1;
+        self.n += 1;
+    }
+
+    /// Computes the empirical progress distribution of the samples.
+    fn estimate(&self) -> Estimate {
+        let n = self.n as f32;
+
+        let (pts, se): (Vec<_>, Vec<_>) = self
+            .reqs
+            .iter()
+            .zip(self.hist.iter().scan(0, |cnt, &h| {
+                *cnt += h;
+                Some(*cnt)
+            }))
+            .map(|(&(p, _), cnt)| {
+                let prob = cnt as f32 / n;
+                ((p, prob), (prob * (1. - prob) / n).sqrt())
+            })
+            .unzip();
+
+        // The points at zero and one are exact.
+        Estimate {
+            pts: with_ends(pts),
+            se: std::iter::once(0.)
+                .chain(se)
+                .chain(std::iter::once(0.))
+                .collect(),
         }
-        DistMode::Beta(alpha, beta) => {
-            let mut beta_rng = XorShiftRng::seed_from_u64(1);
-
-            let dist = Beta::new(alpha as f64, beta as f64).unwrap();
-            for _ in 0..n_samps {
-                samples.push(
-                    dist.sample_iter(&mut beta_rng)
-                        .map(|q| Exp::new(lambda(q as f32)).unwrap().sample(&mut rng))
-                        .map(|x| x.min(800.0) as f32) // prevent overflow
-                        .map(|x| min as f32 * 2f32.powf(x))
-                        .find(|&x| x > (CUM.last().unwrap().1 as f32))
-                        .unwrap(),
-                );
-            }
+    }
+}
+
+/// A progress distribution estimated by sampling from a prior.
+pub struct Estimate {
+    /// The points of the progress distribution.
+    pub pts: Vec<(f32, f32)>,
+    /// The standard error of each point. These assume independent samples, and so overstate the
+    /// error of quasi-random samples.
+    pub se: Vec<f32>,
+}
+
+impl Estimate {
+    /// The largest standard error of any point.
+    fn max_se(&self) -> f32 {
+        self.se.iter().copied().fold(0., f32::max)
+    }
+
+    /// A band of two standard errors around each point, as `(x, lower, upper)` triples.
+    pub fn band(&self) -> Vec<(f32, f32, f32)> {
+        self.pts
+            .iter()
+            .zip(&self.se)
+            .map(|(&(x, y), &se)| (x, bound(y - 2. * se), bound(y + 2. * se)))
+            .collect()
+    }
+}
+
+/// Estimates the progress distribution implied by a mixture of priors by sampling from it.
+///
+/// Returns `None` if the parameters of any component are invalid, if any weight isn't positive, or
+/// if the mixture rules out exceeding the number of insights discovered so far.
+///
+/// # Arguments
+///
+/// - `components: &[(f32, Prior)]` - The weight and prior of each component.
+/// - `sampler: &Sampler` - How to sample from the mixture.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub fn sample_prior(
+    components: &[(f32, Prior)],
+    sampler: &Sampler,
+    insights_cnt: f32,
+) -> Option<Estimate> {
+    let mut q_rng = rng_stream(sampler.seed, 1);
+    let mut x_rng = rng_stream(sampler.seed, 2);
+    let mut halton = Halton::new(&mut rng_stream(sampler.seed, 3));
+    let mut c_rng = rng_stream(sampler.seed, 4);
+
+    // Samples are drawn conditioned on exceeding the insights discovered so far, which is
+    // effectively a Bayesian update.
+    let dist = TruncMixture::new(components, insights_cnt)?;
+
+    let mut tally = Tally::new(insights_cnt);
+
+    loop {
+        for _ in 0..sampler.n_samps.max(1) {
+            let x = match sampler.method {
+                SampleMethod::Random => dist.sample(&mut q_rng, &mut x_rng, &mut c_rng),
+                SampleMethod::Quasi => dist.quantile(halton.next()?),
+            };
+
+            tally.add(x);
         }
-    };
 
-    REQS.iter()
-        .copied()
-        .map(|(p, r)| {
-            let cnt: usize = samples.iter().copied().filter(|&x| x as f32 >= r).count();
-            (p, cnt as f32 / n_samps as f32)
+        let est = tally.estimate();
+
+        match sampler.target_se {
+            Some(target) if est.max_se() > target && tally.n < MAX_SAMPS => continue,
+            _ => return Some(est),
+        }
+    }
+}
+
+/// Re-runs `sample_prior` with several consecutive seeds and measures the Monte Carlo spread of the
+/// resulting curves, i.e., the largest standard deviation across seeds at any point.
+///
+/// # Arguments
+///
+/// - `components: &[(f32, Prior)]` - The weight and prior of each component.
+/// - `sampler: &Sampler` - How to sample from the mixture, starting from its seed.
+/// - `n_seeds: u64` - The number of seeds.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub fn seed_spread(
+    components: &[(f32, Prior)],
+    sampler: &Sampler,
+    n_seeds: u64,
+    insights_cnt: f32,
+) -> Option<f32> {
+    if n_seeds < 2 {
+        return None;
+    }
+
+    let curves = (sampler.seed..sampler.seed + n_seeds)
+        .map(|seed| {
+            sample_prior(components, &Sampler { seed, ..*sampler }, insights_cnt).map(|est| est.pts)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let n = n_seeds as f32;
+
+    (0..curves.first()?.len())
+        .map(|i| {
+            let mean = curves.iter().map(|c| c[i].1).sum::<f32>() / n;
+            let var = curves.iter().map(|c| (c[i].1 - mean).powi(2)).sum::<f32>() / (n - 1.);
+            var.sqrt()
+        })
+        .fold(None, |acc: Option<f32>, sd| {
+            Some(acc.map_or(sd, |acc| acc.max(sd)))
         })
-        .collect()
 }
 
 /// Adds a point to the progress distribution, modifying other points as necessary to preserve
@@ -124,18 +340,6 @@ pub fn pareto(min: u32, n_samps: usize, mode: DistMode) -> Vec<(f32, f32)> {
 /// - `new_y: f32` - The y-coordinate of the point to be added, i.e., the probability the the process
 /// is no more than this much of the way done.
 pub fn add_point(pts: &mut Vec<(f32, f32)>, new_x: f32, new_y: f32) {
-    // The coordinate transformation is just an affine map.
-    // This could be somewhat more robust and allow for dynamically scalable canvas
-    // for drawing/displaying the distribution, but if would require slightly more
-    // complicated state management.
-    let xb = -1. / 9.;
-    let xm = 1. / 450.;
-
-    let ym = -1. / 420.;
-    let yb = 15. / 14.;
-
-    let new_x = xm * new_x + xb;
-    let new_y = ym * new_y + yb;
     pts.push((new_x, new_y));
 
     pts.sort_unstable_by(|(k1, _), (k2, _)| k1.partial_cmp(k2).unwrap());
@@ -154,8 +358,190 @@ pub fn add_point(pts: &mut Vec<(f32, f32)>, new_x: f32, new_y: f32) {
     }
 }
 
-pub fn draw_dist(pts: &[(f32, f32)]) -> Option<()> {
-    let pts = pts.iter().copied();
+/// Adds a freehand stroke to the progress distribution, replacing the points under it and
+/// modifying other points as necessary to preserve monotonicity. Returns whether the stroke was
+/// long enough to add.
+///
+/// The stroke is made monotone by isotonic regression, since it may wobble or double back, then
+/// resampled to evenly spaced points.
+///
+/// # Arguments
+///
+/// - `pts: &mut Vec<(f32, f32)>` - The progress distribution.
+/// - `stroke: &[(f32, f32)]` - The points of the stroke, in the order they were drawn.
+/// - `smooth: bool` - Whether to smooth the stroke with a moving average.
+pub fn add_stroke(pts: &mut Vec<(f32, f32)>, stroke: &[(f32, f32)], smooth: bool) -> bool {
+    let mut stroke: Vec<(f32, f32)> = stroke.iter().map(|&(x, y)| (bound(x), bound(y))).collect();
+    stroke.sort_by(|(x1, _), (x2, _)| x1.partial_cmp(x2).unwrap_or(Ordering::Equal));
+
+    let (x_min, x_max) = match (stroke.first(), stroke.last()) {
+        (Some(&(x_min, _)), Some(&(x_max, _))) if x_max - x_min >= STROKE_SPACING => (x_min, x_max),
+        _ => return false,
+    };
+
+    let ys: Vec<f32> = stroke.iter().map(|&(_, y)| y).collect();
+    let monotone: Vec<(f32, f32)> = stroke
+        .iter()
+        .zip(isotonic(&ys))
+        .map(|(&(x, _), y)| (x, y))
+        .collect();
+
+    let n = ((x_max - x_min) / STROKE_SPACING).ceil() as usize + 1;
+    let xs: Vec<f32> = linspace(x_min, x_max, n).collect();
+    let mut ys = match xs
+        .iter()
+        .map(|&x| prob_from_portion(x, &monotone, Interp::Linear))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(ys) => ys,
+        None => return false,
+    };
+
+    if smooth {
+        ys = moving_average(&ys, 2);
+    }
+
+    // Points needed to cover every portion are kept, and overwritten if the stroke reaches them.
+    pts.retain(|&(x, _)| is_end(x) || x < x_min || x > x_max);
+
+    for (x, y) in xs.into_iter().zip(ys) {
+        add_point(pts, x, y);
+    }
+    pts.dedup();
+
+    true
+}
+
+/// Whether a point of the progress distribution with x-coordinate `x` is at zero or one, so that
+/// it is needed to cover every portion.
+fn is_end(x: f32) -> bool {
+    x <= 0. || x >= 1.
+}
+
+/// Finds the point of the progress distribution nearest to a location on the chart.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - The progress distribution.
+/// - `x: f32` - The x-coordinate of the location.
+/// - `y: f32` - The y-coordinate of the location.
+pub fn nearest_point(pts: &[(f32, f32)], x: f32, y: f32) -> Option<(usize, f32)> {
+    pts.iter()
+        .map(|&(px, py)| (px - x).hypot(py - y))
+        .enumerate()
+        .min_by(|(_, d1), (_, d2)| d1.partial_cmp(d2).unwrap_or(Ordering::Equal))
+}
+
+/// Removes a point from the progress distribution, unless it is at zero or one. Returns whether the
+/// point was removed.
+///
+/// # Arguments
+///
+/// - `pts: &mut Vec<(f32, f32)>` - The progress distribution.
+/// - `i: usize` - The index of the point to remove.
+pub fn remove_point(pts: &mut Vec<(f32, f32)>, i: usize) -> bool {
+    match pts.get(i) {
+        Some(&(x, _)) if !is_end(x) => {
+            pts.remove(i);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Moves a point of the progress distribution, modifying other points as necessary to preserve
+/// monotonicity. Points at zero and one can only be moved vertically, and other points stay
+/// strictly between them. Returns the index of the point after moving it, or nothing if the index
+/// or coordinates are invalid.
+///
+/// # Arguments
+///
+/// - `pts: &mut Vec<(f32, f32)>` - The progress distribution.
+/// - `i: usize` - The index of the point to move.
+/// - `new_x: f32` - The new x-coordinate of the point.
+/// - `new_y: f32` - The new y-coordinate of the point.
+pub fn move_point(pts: &mut Vec<(f32, f32)>, i: usize, new_x: f32, new_y: f32) -> Option<usize> {
+    if i >= pts.len() || !new_x.is_finite() || !new_y.is_finite() {
+        return None;
+    }
+
+    let (old_x, _) = pts.remove(i);
+    let new_x = if is_end(old_x) {
+        old_x
+    } else {
+        // Otherwise the point would duplicate an end, and couldn't be deleted.
+        new_x.clamp(f32::EPSILON, 1. - f32::EPSILON)
+    };
+
+    add_point(pts, new_x, new_y);
+
+    pts.iter().rposition(|&(x, _)| x == new_x)
+}
+
+/// Makes a progress distribution from a collection of points, adding points at zero and one if
+/// they are missing. Returns nothing if the points are out of bounds or not monotone.
+///
+/// # Arguments
+///
+/// - `pts: Vec<(f32, f32)>` - The points, in any order.
+pub fn from_points(mut pts: Vec<(f32, f32)>) -> Option<Vec<(f32, f32)>> {
+    let in_bounds = |v: f32| (0. ..=1.).contains(&v);
+    if !pts.iter().all(|&(x, y)| in_bounds(x) && in_bounds(y)) {
+        return None;
+    }
+
+    pts.sort_by(|(x1, y1), (x2, y2)| {
+        x1.partial_cmp(x2)
+            .unwrap()
+            .then(y1.partial_cmp(y2).unwrap())
+    });
+
+    if pts.windows(2).any(|w| w[1].1 < w[0].1) {
+        return None;
+    }
+
+    if !matches!(pts.first(), Some(&(x, _)) if x <= 0.) {
+        pts.insert(0, (0., 0.));
+    }
+    if !matches!(pts.last(), Some(&(x, _)) if x >= 1.) {
+        pts.push((1., 1.));
+    }
+
+    Some(pts)
+}
+
+/// Draws the progress distribution.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - The progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `band: Option<&[(f32, f32, f32)]>` - The Monte Carlo error band, if the points were sampled.
+/// - `x_scale: Scale` - The scale of the proportion axis.
+/// - `y_scale: Scale` - The scale of the probability axis.
+///
+/// Returns the map from pixels on the canvas to coordinates on the chart, undoing the scales.
+pub fn draw_dist(
+    pts: &[(f32, f32)],
+    interp: Interp,
+    band: Option<&[(f32, f32, f32)]>,
+    x_scale: Scale,
+    y_scale: Scale,
+) -> Option<CoordFn> {
+    // Straight lines stay straight only if both axes are linear, otherwise the curve is evaluated
+    // at points evenly spaced along the proportion axis.
+    let pts = match (interp, x_scale, y_scale) {
+        (Interp::Linear, Scale::Linear, Scale::Linear) => pts.to_vec(),
+        _ => {
+            let range = x_scale.range();
+            linspace(range.start, range.end, 501)
+                .map(|u| {
+                    let prob = prob_from_portion(x_scale.unscaled(u), pts, interp)?;
+                    Some((u, y_scale.scaled(prob)))
+                })
+                .collect::<Option<Vec<_>>>()?
+        }
+    };
 
     // gracefully fail, and avoid the code gen bloat that happens with panics.
     let backend = CanvasBackend::new("progress_dist")?;
@@ -170,19 +556,88 @@ pub fn draw_dist(pts: &[(f32, f32)]) -> Option<()> {
         .caption("Draw a progress distribution", font)
         .x_label_area_size(50)
         .y_label_area_size(50)
-        .build_ranged(0.0..1f32, 0.0..1f32)
+        .build_ranged(x_scale.range(), y_scale.range())
         .ok()?;
 
     chart
         .configure_mesh()
+        .x_label_formatter(&|u| x_scale.label(*u))
+        .y_label_formatter(&|u| y_scale.label(*u))
         .x_desc("Proportion of required insights that have been discovered")
         .y_desc("Pr(no more than this much of the way done)")
         .draw()
         .ok()?;
 
+    if let Some(band) = band {
+        let outline: Vec<(f32, f32)> = band
+            .iter()
+            .map(|&(x, _, hi)| (x, hi))
+            .chain(band.iter().rev().map(|&(x, lo, _)| (x, lo)))
+            .map(|(x, y)| (x_scale.scaled(x), y_scale.scaled(y)))
+            .collect();
+
+        chart
+            .draw_series(std::iter::once(Polygon::new(
+                outline,
+                &RGBColor(0, 136, 238).mix(0.2),
+            )))
+            .ok()?;
+    }
+
     chart
         .draw_series(LineSeries::new(pts, &RGBColor(0, 136, 238)))
         .ok()?;
 
-    Some(())
+    let trans = chart.into_coord_trans();
+    Some(Box::new(move |pixel| {
+        trans(pixel).map(|(u, w)| (x_scale.unscaled(u), y_scale.unscaled(w)))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prior::{DistMode, Min};
+
+    /// Checks that sampling a prior agrees with computing it exactly, to within a few standard
+    /// errors of the samples.
+    fn assert_agrees(prior: Prior, method: SampleMethod) {
+        let components = [(1., prior)];
+        let sampler = Sampler {
+            n_samps: 20_000,
+            seed: 7,
+            target_se: None,
+            method,
+        };
+
+        let exact = exact_prior(&components, 60.).unwrap();
+        let est = sample_prior(&components, &sampler, 60.).unwrap();
+
+        assert_eq!(exact.len(), est.pts.len());
+        for ((&(x, expected), &(_, actual)), &se) in exact.iter().zip(&est.pts).zip(&est.se) {
+            // The slack covers the discretization of an uncertain minimum by the exact version.
+            assert!(
+                (actual - expected).abs() <= 4. * se + 2e-3,
+                "{} vs {} at {}",
+                actual,
+                expected,
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn sampled_prior_matches_exact() {
+        let priors = [
+            Prior::Pareto(Min::Fixed(10), DistMode::Uniform),
+            Prior::Pareto(Min::Fixed(10), DistMode::Q(0.3)),
+            Prior::Pareto(Min::Fixed(10), DistMode::Beta(1.5, 4.)),
+            Prior::Pareto(Min::LogUniform(5, 200), DistMode::Q(0.3)),
+        ];
+
+        for &prior in priors.iter() {
+            assert_agrees(prior, SampleMethod::Random);
+            assert_agrees(prior, SampleMethod::Quasi);
+        }
+    }
 }
@@ -0,0 +1,140 @@
+use crate::{
+    timeline::{resample, Interp},
+    util::{beta_inc, normal_sf},
+};
+use differential_evolution2::self_adaptive_de;
+use itertools_num::linspace;
+use std::fmt;
+
+/// Parametric families that can be fit to a progress distribution.
+#[derive(Clone, Copy)]
+pub enum Family {
+    /// Beta distribution.
+    Beta,
+    /// Logit-normal distribution, i.e., the logit of the proportion is normal.
+    LogitNormal,
+}
+
+/// A parametric distribution fit to a progress distribution.
+pub struct Fit {
+    /// Which family the distribution belongs to.
+    pub family: Family,
+    /// The parameters of the distribution: `(alpha, beta)` for a Beta, and the mean and standard
+    /// deviation of the logit for a logit-normal.
+    pub params: (f32, f32),
+    /// The root mean square difference between the fit and the points it was fit to.
+    pub rmse: f32,
+}
+
+impl Family {
+    /// Computes the cumulative distribution function.
+    ///
+    /// # Arguments
+    ///
+    /// - `params: (f32, f32)` - The parameters of the distribution.
+    /// - `x: f32` - The proportion of required insights that have been discovered.
+    fn cdf(self, params: (f32, f32), x: f32) -> f32 {
+        if x <= 0. {
+            return 0.;
+        } else if x >= 1. {
+            return 1.;
+        }
+
+        let (a, b) = (params.0 as f64, params.1 as f64);
+        let x = x as f64;
+
+        let cdf = match self {
+            Family::Beta => beta_inc(a, b, x),
+            Family::LogitNormal => 1. - normal_sf(((x / (1. - x)).ln() - a) / b),
+        };
+
+        cdf as f32
+    }
+}
+
+impl Fit {
+    /// Computes the cumulative distribution function of the fit.
+    pub fn cdf(&self, x: f32) -> f32 {
+        self.family.cdf(self.params, x)
+    }
+
+    /// Evaluates the fit at evenly spaced points, as a progress distribution.
+    pub fn points(&self) -> Vec<(f32, f32)> {
+        linspace(0f32, 1f32, 201)
+            .map(|x| (x, self.cdf(x)))
+            .collect()
+    }
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = self.params;
+        match self.family {
+            Family::Beta => write!(f, "Beta(alpha = {:.3}, beta = {:.3})", a, b)?,
+            Family::LogitNormal => write!(f, "LogitNormal(mu = {:.3}, sigma = {:.3})", a, b)?,
+        };
+
+        write!(f, ", RMS error {:.4}", self.rmse)
+    }
+}
+
+/// Fits a parametric distribution to points of a progress distribution by least squares, using a
+/// simple genetic optimizer.
+///
+/// Returns `None` if there are fewer than two points or the optimizer fails.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - The points to fit, as `(proportion, probability)` pairs.
+/// - `family: Family` - Which family to fit.
+pub fn fit(pts: &[(f32, f32)], family: Family) -> Option<Fit> {
+    if pts.len() < 2 {
+        return None;
+    }
+
+    // The Beta parameters are searched for on a log scale to keep them positive, as is the
+    // standard deviation of the logit-normal.
+    let unpack = |pos: &[f32]| -> (f32, f32) {
+        match family {
+            Family::Beta => (pos[0].exp(), pos[1].exp()),
+            Family::LogitNormal => (pos[0], pos[1].exp()),
+        }
+    };
+
+    let sse = |params: (f32, f32)| -> f32 {
+        pts.iter()
+            .map(|&(x, y)| (family.cdf(params, x) - y).powi(2))
+            .sum()
+    };
+
+    let initial: Vec<(f32, f32)> = match family {
+        Family::Beta => vec![(-3., 3.), (-3., 3.)],
+        Family::LogitNormal => vec![(-8., 4.), (-3., 2.)],
+    };
+
+    let mut de = self_adaptive_de(initial, |pos: &[f32]| sse(unpack(pos)));
+
+    let n_iters = 5_000;
+
+    de.iter().nth(n_iters);
+    let (_, pos) = de.best()?;
+    let params = unpack(pos);
+
+    Some(Fit {
+        family,
+        params,
+        rmse: (sse(params) / pts.len() as f32).sqrt(),
+    })
+}
+
+/// Fits a parametric distribution to a whole progress distribution, rather than just its points, by
+/// evaluating it at evenly spaced proportions.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - The progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `family: Family` - Which family to fit.
+pub fn fit_curve(pts: &[(f32, f32)], interp: Interp, family: Family) -> Option<Fit> {
+    fit(&resample(pts, interp, 101)?, family)
+}
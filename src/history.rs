@@ -0,0 +1,170 @@
+/// The most edits that can be undone.
+const MAX_HISTORY: usize = 100;
+
+/// Kinds of edits recorded in the history.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Edit {
+    /// Adding, deleting or replacing points of the progress distribution.
+    Points,
+    /// Moving a point of the progress distribution.
+    Move,
+    /// Choosing a preset prior, or changing the mixture of them.
+    Prior,
+    /// Changing the range of years the curve is fit to.
+    YearRange,
+    /// Changing the regression mode.
+    Mode,
+    /// Changing the number of insights discovered so far.
+    InsightsCnt,
+}
+
+impl Edit {
+    /// Whether edits of this kind usually come in a continuous stream, e.g., while dragging, in
+    /// which case the stream is undone as a whole.
+    fn continuous(self) -> bool {
+        match self {
+            Edit::Move | Edit::YearRange => true,
+            Edit::Points | Edit::Prior | Edit::Mode | Edit::InsightsCnt => false,
+        }
+    }
+}
+
+/// A history of snapshots for undoing and redoing edits.
+pub struct History<T> {
+    /// Snapshots from before each edit, the most recent last.
+    undo: Vec<T>,
+    /// Snapshots from before each undo, the most recent last.
+    redo: Vec<T>,
+    /// The kind of continuous edit in progress, if any. Further edits of this kind are merged into
+    /// it.
+    open: Option<Edit>,
+}
+
+impl<T> History<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: None,
+        }
+    }
+
+    /// Records an edit. This discards anything that could have been redone.
+    ///
+    /// # Arguments
+    ///
+    /// - `kind: Edit` - The kind of the edit.
+    /// - `before: T` - A snapshot from before the edit.
+    pub fn record(&mut self, kind: Edit, before: T) {
+        if self.open == Some(kind) {
+            return;
+        }
+
+        if self.undo.len() == MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+
+        self.open = if kind.continuous() { Some(kind) } else { None };
+    }
+
+    /// Ends the continuous edit in progress, if any, so that the next edit is undone separately.
+    pub fn close(&mut self) {
+        self.open = None;
+    }
+
+    /// Undoes the most recent edit, returning the snapshot to go back to.
+    ///
+    /// # Arguments
+    ///
+    /// - `current: T` - A snapshot of the current state, to go back to on redoing.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let before = self.undo.pop()?;
+        self.redo.push(current);
+        self.open = None;
+        Some(before)
+    }
+
+    /// Redoes the most recently undone edit, returning the snapshot to go back to.
+    ///
+    /// # Arguments
+    ///
+    /// - `current: T` - A snapshot of the current state, to go back to on undoing.
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let after = self.redo.pop()?;
+        self.undo.push(current);
+        self.open = None;
+        Some(after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_then_redo() {
+        let mut history = History::new();
+        history.record(Edit::Points, 0);
+        history.record(Edit::Prior, 1);
+
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), None);
+    }
+
+    #[test]
+    fn continuous_edits_undo_together() {
+        let mut history = History::new();
+        history.record(Edit::Points, 0);
+        for state in 1..5 {
+            history.record(Edit::Move, state);
+        }
+
+        assert_eq!(history.undo(5), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+    }
+
+    #[test]
+    fn close_splits_continuous_edits() {
+        let mut history = History::new();
+        history.record(Edit::Move, 0);
+        history.record(Edit::Move, 1);
+        history.close();
+        history.record(Edit::Move, 2);
+        history.record(Edit::Move, 3);
+
+        assert_eq!(history.undo(4), Some(2));
+        assert_eq!(history.undo(2), Some(0));
+    }
+
+    #[test]
+    fn history_is_limited() {
+        let mut history = History::new();
+        for state in 0..MAX_HISTORY + 10 {
+            history.record(Edit::Points, state);
+        }
+
+        let mut current = MAX_HISTORY + 10;
+        while let Some(before) = history.undo(current) {
+            current = before;
+        }
+        assert_eq!(current, 10);
+    }
+
+    #[test]
+    fn new_edit_discards_redo() {
+        let mut history = History::new();
+        history.record(Edit::Points, 0);
+        assert_eq!(history.undo(1), Some(0));
+
+        history.record(Edit::Mode, 0);
+        assert_eq!(history.redo(2), None);
+        assert_eq!(history.undo(2), Some(0));
+    }
+}
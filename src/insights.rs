@@ -1,4 +1,7 @@
-use crate::{util::fit_sigmoid, CurveFn, RegMode};
+use crate::{
+    util::{fit_sigmoid, invert, sigmoid},
+    CoordFn, CurveFn, InvFn, RegMode,
+};
 use linreg::linear_regression_of;
 use plotters::prelude::*;
 
@@ -13,13 +16,15 @@ use plotters::prelude::*;
 /// - `model_curve: &Option<CurveFn>` - A function that computes the curve fit to the insights
 /// data.
 /// - `curve_repr` - A string representing the equation of the curve.
+///
+/// Returns the map from pixels on the canvas to coordinates on the chart.
 pub fn draw_insights(
     min_year: i16,
     max_year: i16,
     sub_cum: &[(i16, u8)],
     model_curve: &Option<CurveFn>,
     curve_repr: String,
-) -> Option<()> {
+) -> Option<CoordFn> {
     if sub_cum.len() < 5 {
         return None;
     }
@@ -34,7 +39,7 @@ pub fn draw_insights(
     // `model_curve` is `None`.
     let model_curve = match model_curve {
         Some(mc) => {
-            insights_ub = insights_ub.max(mc(sub_cum.last()?.0 as i32));
+            insights_ub = insights_ub.max(mc(sub_cum.last()?.0 as f32));
             Some(mc)
         }
         None => None,
@@ -84,7 +89,7 @@ pub fn draw_insights(
         chart
             .draw_series(LineSeries::new(
                 (sub_cum[0].0..=sub_cum.last()?.0)
-                    .map(|y| (y, curve(y as i32)))
+                    .map(|y| (y, curve(y as f32)))
                     .map(|(year, pred)| (year as i32, pred))
                     .filter(|(_, pred)| *pred > 0.),
                 &RGBColor(238, 85, 0),
@@ -100,18 +105,39 @@ pub fn draw_insights(
         .draw()
         .ok()?;
 
-    Some(())
+    let trans = chart.into_coord_trans();
+    Some(Box::new(move |pixel| {
+        trans(pixel).map(|(year, cnt)| (year as f32, cnt))
+    }))
+}
+
+/// Boxes a fit curve along with its numeric inverse.
+///
+/// # Arguments
+///
+/// - `curve: F` - The fit curve.
+/// - `start: f32` - The year to start searching from when inverting the curve.
+fn with_inverse<F>(curve: F, start: f32) -> (CurveFn, InvFn)
+where
+    F: Fn(f32) -> f32 + Copy + 'static,
+{
+    (
+        Box::new(curve),
+        Box::new(move |cnt| invert(curve, cnt, start)),
+    )
 }
 
 /// Fits
-pub fn reg(mode: &RegMode, sub_cum: &[(i16, u8)]) -> Option<(String, CurveFn, CurveFn)> {
+pub fn reg(mode: &RegMode, sub_cum: &[(i16, u8)]) -> Option<(String, CurveFn, InvFn)> {
     use RegMode::*;
+
+    let start = sub_cum.last()?.0 as f32;
+
     match mode {
         Linear => {
             let (m, b): (f32, f32) = linear_regression_of(sub_cum)?;
 
-            let func = Box::new(move |y| m * y as f32 + b);
-            let inv_func = Box::new(move |cnt| (cnt as f32 - b) / m);
+            let (func, inv_func) = with_inverse(move |y| m * y + b, start);
 
             let repr = format!("y = {m:.2}x + {b:.2}", m = m, b = b);
             Some((repr, func, inv_func))
@@ -127,8 +153,8 @@ pub fn reg(mode: &RegMode, sub_cum: &[(i16, u8)]) -> Option<(String, CurveFn, Cu
 
             let (rate, constant): (f32, f32) = linear_regression_of(&log_cum)?;
 
-            let func = Box::new(move |y| (rate * y as f32).exp() * (constant).exp());
-            let inv_func = Box::new(move |cnt| (cnt as f32 / constant.exp()).ln() / rate);
+            let (func, inv_func) =
+                with_inverse(move |y| (rate * y).exp() * (constant).exp(), start);
 
             let repr = format!(
                 "y = {constant:.3E} e^({rate:.5} * x)",
@@ -146,8 +172,7 @@ pub fn reg(mode: &RegMode, sub_cum: &[(i16, u8)]) -> Option<(String, CurveFn, Cu
 
             let (m, k, b) = fit_sigmoid(&pts)?;
 
-            let func = Box::new(move |y| m / (1. + (-k * (y as f32 - b)).exp()));
-            let inv_func = Box::new(move |cnt| ((b * k) - (m / cnt as f32 - 1.).ln()) / k);
+            let (func, inv_func) = with_inverse(move |y| sigmoid(m, k, b, y), start);
 
             let repr = format!(
                 "y = {m:.1} / (1 + e^(-{k:.2E} * (x - {b:.1})))",
@@ -5,47 +5,127 @@ extern crate wee_alloc;
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 use data::CUM;
+use history::{Edit, History};
+use prior::{DistMode, Min, Prior};
 use wasm_bindgen::prelude::*;
 
 mod data;
 mod dist;
+mod fit;
+mod history;
 mod insights;
+mod prior;
+mod required;
+mod sample;
+mod scale;
 mod timeline;
 mod util;
 
 /// Type-alias for the curves fit to the insights data.
-type CurveFn = Box<dyn Fn(i32) -> f32>;
+type CurveFn = Box<dyn Fn(f32) -> f32>;
+
+/// Type-alias for the inverses of the curves fit to the insights data.
+type InvFn = Box<dyn Fn(f32) -> util::Crossing>;
+
+/// Type-alias for the maps from pixels on a canvas to coordinates on the chart drawn to it.
+type CoordFn = Box<dyn Fn((i32, i32)) -> Option<(f32, f32)>>;
 
 /// Which model to fit the insights data to.
+#[derive(Clone, Copy, PartialEq)]
 pub enum RegMode {
     Linear,
     Exponential,
     Sigmoidal,
 }
 
+impl RegMode {
+    /// Whether curves of this kind level off, so that some counts are never reached. The others
+    /// reach every count eventually.
+    fn saturates(self) -> bool {
+        self == RegMode::Sigmoidal
+    }
+}
+
 #[wasm_bindgen]
 /// The state of the insights demo.
 pub struct State {
     /// The points the specify the prior distribution.
     prog_points: Vec<(f32, f32)>,
+    /// The Monte Carlo error band around the points, if they were sampled.
+    prog_band: Option<Vec<(f32, f32, f32)>>,
     /// The minimum year to display and fit the curve to.
     year_min: i16,
     /// The maximum year to display and fit the curve to.
     year_max: i16,
     /// An upper bound for how far the timeline projection goes.
     last: i32,
+    /// The year the timeline projection starts from.
+    now: i32,
+    /// The number of insights discovered so far.
+    insights_cnt: u32,
+    /// How to show the timeline.
+    timeline_view: timeline::TimelineView,
     /// Which model to fit the insights data to.
     mode: RegMode,
     /// The number of samples to take from the prior distribution.
-    num_samples: u16,
+    num_samples: u32,
+    /// The seed for sampling from the prior distribution.
+    seed: u32,
+    /// If set, keep sampling from the prior distribution until the standard error of every point
+    /// is at most this.
+    target_se: Option<f32>,
+    /// Where the randomness for sampling from the prior distribution comes from.
+    method: dist::SampleMethod,
+    /// How to interpolate between the points of the progress distribution.
+    interp: timeline::Interp,
+    /// The scale of the proportion axis of the progress distribution chart.
+    x_scale: scale::Scale,
+    /// The scale of the probability axis of the progress distribution chart.
+    y_scale: scale::Scale,
+    /// Whether to compute preset priors exactly instead of sampling from them.
+    exact: bool,
+    /// If set, the minimum plausible number of insights of Pareto priors is log-uniform between
+    /// the minimum passed to the preset and this.
+    min_upper: Option<u32>,
+    /// The weight and prior of each component of the mixture of preset priors.
+    components: Vec<(f32, Prior)>,
+    /// If set, preset priors are added to the mixture with this weight instead of replacing it.
+    mix_weight: Option<f32>,
     /// The fit curve.
     model_curve: Option<CurveFn>,
     /// A string representation of the equation describing the fit curve.
     curve_repr: Option<String>,
     /// The inverse of the fit curve (used for the projection).
-    inv_curve: Option<CurveFn>,
+    inv_curve: Option<InvFn>,
     /// The subset of the cumulative distribution of insights under consideration.
     sub_cum: Vec<(i16, u8)>,
+    /// The map from pixels to coordinates on the progress distribution chart, once drawn.
+    dist_coords: Option<CoordFn>,
+    /// The map from pixels to coordinates on the insights chart, once drawn.
+    insights_coords: Option<CoordFn>,
+    /// The implied timeline, as of the last time the timeline chart was drawn.
+    timeline: Option<timeline::Timeline>,
+    /// The map from pixels to coordinates on the timeline chart, once drawn.
+    timeline_coords: Option<CoordFn>,
+    /// The map from pixels to coordinates on the chart of the required number of insights, once
+    /// drawn.
+    required_coords: Option<CoordFn>,
+    /// The freehand stroke being drawn on the progress distribution chart, in chart coordinates.
+    stroke: Vec<(f32, f32)>,
+    /// The history of edits, for undoing and redoing them.
+    history: History<Snapshot>,
+}
+
+/// The parts of the state that can be edited, so that edits can be undone. The fields are as in
+/// `State`.
+struct Snapshot {
+    prog_points: Vec<(f32, f32)>,
+    prog_band: Option<Vec<(f32, f32, f32)>>,
+    components: Vec<(f32, Prior)>,
+    year_min: i16,
+    year_max: i16,
+    mode: RegMode,
+    insights_cnt: u32,
 }
 
 /// Takes the subset of the cumulative distribution of insights in a given time interval.
@@ -62,6 +142,30 @@ fn make_sub_cum(year_min: i16, year_max: i16) -> Vec<(i16, u8)> {
         .collect()
 }
 
+/// The number of insights discovered so far according to the data.
+fn data_cnt() -> u32 {
+    CUM.last().map_or(0, |&(_, cnt)| cnt as u32)
+}
+
+/// Parses the string representation of a parametric family. Returns nothing if it isn't one.
+fn parse_family(family: &str) -> Option<fit::Family> {
+    match family {
+        "Beta" => Some(fit::Family::Beta),
+        "LogitNormal" => Some(fit::Family::LogitNormal),
+        _ => None,
+    }
+}
+
+/// Parses the string representation of an axis scale. Returns nothing if it isn't one.
+fn parse_scale(scale: &str) -> Option<scale::Scale> {
+    match scale {
+        "Linear" => Some(scale::Scale::Linear),
+        "Log" => Some(scale::Scale::Log),
+        "Logit" => Some(scale::Scale::Logit),
+        _ => None,
+    }
+}
+
 #[wasm_bindgen]
 impl State {
     #[allow(clippy::new_without_default)]
@@ -74,15 +178,36 @@ impl State {
 
         State {
             prog_points: vec![(0., 0.), (1., 1.)],
+            prog_band: None,
             year_min,
             year_max,
             num_samples: 5000,
+            seed: 1,
+            target_se: None,
+            method: dist::SampleMethod::Random,
+            interp: timeline::Interp::Linear,
+            x_scale: scale::Scale::Linear,
+            y_scale: scale::Scale::Linear,
+            exact: false,
+            min_upper: None,
+            components: Vec::new(),
+            mix_weight: None,
             last: 2200,
+            now: 2020,
+            insights_cnt: data_cnt(),
+            timeline_view: timeline::TimelineView::Cumulative,
             mode,
             curve_repr: Some(curve_repr),
             model_curve: Some(curve),
             inv_curve: Some(inv_curve),
             sub_cum,
+            dist_coords: None,
+            insights_coords: None,
+            timeline: None,
+            timeline_coords: None,
+            required_coords: None,
+            stroke: Vec::new(),
+            history: History::new(),
         }
     }
 
@@ -93,11 +218,22 @@ impl State {
     /// - `year_min: i16` - The lower bound of the interval.
     /// - `year_max: i16` - The upper bound of the interval.
     pub fn set_year_range(&mut self, year_min: i16, year_max: i16) {
+        if (year_min, year_max) == (self.year_min, self.year_max) {
+            return;
+        }
+
+        let before = self.snapshot();
         self.year_min = year_min;
         self.year_max = year_max;
 
         self.sub_cum = make_sub_cum(year_min, year_max);
         self.set_curve();
+        self.history.record(Edit::YearRange, before);
+    }
+
+    /// The time interval for insights, as `[year_min, year_max]`.
+    pub fn year_range(&self) -> Vec<i16> {
+        vec![self.year_min, self.year_max]
     }
 
     /// Sets the regression mode for curve fitting.
@@ -106,13 +242,30 @@ impl State {
     ///
     /// - `mode: &str` - String representation of mode.
     pub fn set_mode(&mut self, mode: &str) {
-        self.mode = match mode {
+        let mode = match mode {
             "Linear" => RegMode::Linear,
             "Exponential" => RegMode::Exponential,
             "Sigmoidal" => RegMode::Sigmoidal,
             _ => unimplemented!(),
         };
+        if mode == self.mode {
+            return;
+        }
+
+        let before = self.snapshot();
+        self.mode = mode;
         self.set_curve();
+        self.history.record(Edit::Mode, before);
+    }
+
+    /// String representation of the regression mode for curve fitting.
+    pub fn mode(&self) -> String {
+        match self.mode {
+            RegMode::Linear => "Linear",
+            RegMode::Exponential => "Exponential",
+            RegMode::Sigmoidal => "Sigmoidal",
+        }
+        .into()
     }
 
     /// Sets upper bound for the last year in the projection.
@@ -124,43 +277,468 @@ impl State {
         self.last = last;
     }
 
-    /// Adds a point to the progress distribution.
+    /// Sets the year the timeline projection starts from. Returns whether it was before the upper
+    /// bound for the last year in the projection; otherwise it is ignored.
+    ///
+    /// # Argument
+    ///
+    /// - `now: i32` - The year.
+    pub fn set_now(&mut self, now: i32) -> bool {
+        if now >= self.last {
+            return false;
+        }
+        self.now = now;
+        true
+    }
+
+    /// Sets the number of insights discovered so far, which the preset priors are conditioned on
+    /// and the projection starts from. Zero uses the number in the data.
+    ///
+    /// If the progress distribution comes from preset priors, they are conditioned on the new
+    /// number. If that fails, the distribution is kept, but no longer counts as coming from them.
+    ///
+    /// # Argument
+    ///
+    /// - `cnt: u32` - The number of insights.
+    pub fn set_insights_cnt(&mut self, cnt: u32) {
+        let cnt = if cnt > 0 { cnt } else { data_cnt() };
+        if cnt == self.insights_cnt {
+            return;
+        }
+
+        let before = self.snapshot();
+        self.insights_cnt = cnt;
+
+        let components = std::mem::take(&mut self.components);
+        if !components.is_empty() && self.apply_components(&components) {
+            self.components = components;
+        }
+
+        self.history.record(Edit::InsightsCnt, before);
+    }
+
+    /// The number of insights discovered so far.
+    pub fn insights_cnt(&self) -> u32 {
+        self.insights_cnt
+    }
+
+    /// Sets how to show the timeline. Returns whether the view was recognised; others are ignored.
+    ///
+    /// # Argument
+    ///
+    /// - `view: &str` - String representation of the view.
+    pub fn set_timeline_view(&mut self, view: &str) -> bool {
+        self.timeline_view = match view {
+            "Cumulative" => timeline::TimelineView::Cumulative,
+            "Year" => timeline::TimelineView::Density(1),
+            "Decade" => timeline::TimelineView::Density(10),
+            _ => return false,
+        };
+        true
+    }
+
+    /// Adds the point under a pixel of the progress distribution chart to the progress
+    /// distribution. Pixels outside the plotting area are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn add_point(&mut self, x: f32, y: f32) {
+        if let Some(new) = self.chart_coords("Dist", x, y) {
+            let before = self.snapshot();
+            dist::add_point(&mut self.prog_points, new[0], new[1]);
+            self.hand_edited(Edit::Points, before);
+        }
+    }
+
+    /// Starts a freehand stroke on the progress distribution chart.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn begin_stroke(&mut self, x: f32, y: f32) {
+        self.stroke.clear();
+        self.extend_stroke(x, y);
+    }
+
+    /// Extends the freehand stroke to a pixel of the progress distribution chart. Pixels outside
+    /// the plotting area are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn extend_stroke(&mut self, x: f32, y: f32) {
+        if let Some(at) = self.chart_coords("Dist", x, y) {
+            self.stroke.push((at[0], at[1]));
+        }
+    }
+
+    /// Ends the freehand stroke, replacing the points of the progress distribution under it with
+    /// a monotone resampling of it. Returns whether the stroke was long enough to add.
+    ///
+    /// # Arguments
+    ///
+    /// - `smooth: bool` - Whether to smooth the stroke.
+    pub fn end_stroke(&mut self, smooth: bool) -> bool {
+        let stroke = std::mem::take(&mut self.stroke);
+
+        let before = self.snapshot();
+        let added = dist::add_stroke(&mut self.prog_points, &stroke, smooth);
+        if added {
+            self.hand_edited(Edit::Points, before);
+        }
+        added
+    }
+
+    /// Adds the point under a pixel of the chart of the total number of insights required to the
+    /// progress distribution. Pixels outside the plotting area are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn add_required_point(&mut self, x: f32, y: f32) {
+        let insights_cnt = self.insights_cnt as f32;
+
+        if let Some(new) = self.chart_coords("Required", x, y) {
+            let (new_x, new_y) = required::to_progress(new[0], new[1], insights_cnt);
+
+            let before = self.snapshot();
+            dist::add_point(&mut self.prog_points, new_x, new_y);
+            self.hand_edited(Edit::Points, before);
+        }
+    }
+
+    /// Converts a pixel of one of the charts to coordinates on that chart. Returns nothing if the
+    /// pixel is outside the plotting area, the chart hasn't been drawn yet, or it isn't recognised.
+    ///
+    /// # Arguments
+    ///
+    /// - `chart: &str` - String representation of the chart.
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn chart_coords(&self, chart: &str, x: f32, y: f32) -> Option<Vec<f32>> {
+        let coords = match chart {
+            "Dist" => &self.dist_coords,
+            "Insights" => &self.insights_coords,
+            "Timeline" => &self.timeline_coords,
+            "Required" => &self.required_coords,
+            _ => return None,
+        };
+
+        let (x, y) = coords.as_ref()?((x.round() as i32, y.round() as i32))?;
+        Some(vec![x, y])
+    }
+
+    /// The points of the progress distribution, flattened into `[x0, y0, x1, y1, ...]`.
+    pub fn points(&self) -> Vec<f32> {
+        self.prog_points
+            .iter()
+            .flat_map(|&(x, y)| vec![x, y])
+            .collect()
+    }
+
+    /// Finds the point of the progress distribution nearest to a pixel of its chart.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `radius: f32` - How far away a point can be, as a proportion of the chart, if positive.
+    pub fn nearest_point(&self, x: f32, y: f32, radius: f32) -> Option<usize> {
+        let at = self.chart_coords("Dist", x, y)?;
+
+        // Distances are measured as shown on the chart.
+        let shown = |(x, y): (f32, f32)| (self.x_scale.to_unit(x), self.y_scale.to_unit(y));
+        let pts: Vec<(f32, f32)> = self.prog_points.iter().copied().map(shown).collect();
+        let (at_x, at_y) = shown((at[0], at[1]));
+        let (i, dist) = dist::nearest_point(&pts, at_x, at_y)?;
+
+        if radius <= 0. || dist <= radius {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    /// Deletes a point from the progress distribution. The points at zero and one can't be
+    /// deleted. Returns whether the point was deleted.
     ///
     /// # Arguments
     ///
-    /// - `new_x: f32` - The x-coordinate of the point to be added, i.e., the proportion of required
-    /// insights that have been discovered.
-    /// - `new_y: f32` - The y-coordinate of the point to be added, i.e., the probability the the process
-    /// is no more than this much of the way done.
-    pub fn add_point(&mut self, new_x: f32, new_y: f32) {
-        dist::add_point(&mut self.prog_points, new_x, new_y);
+    /// - `index: usize` - The index of the point to delete.
+    pub fn delete_point(&mut self, index: usize) -> bool {
+        let before = self.snapshot();
+        let removed = dist::remove_point(&mut self.prog_points, index);
+        if removed {
+            self.hand_edited(Edit::Points, before);
+        }
+        removed
+    }
+
+    /// Deletes the point of the progress distribution nearest to a pixel of its chart. Returns
+    /// whether a point was deleted.
+    ///
+    /// # Arguments
+    ///
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn delete_nearest(&mut self, x: f32, y: f32) -> bool {
+        match self.nearest_point(x, y, 0.) {
+            Some(i) => self.delete_point(i),
+            None => false,
+        }
+    }
+
+    /// Drags a point of the progress distribution to a pixel of its chart, modifying other points
+    /// as necessary to preserve monotonicity. Returns the index of the point after moving it.
+    ///
+    /// # Arguments
+    ///
+    /// - `index: usize` - The index of the point to move.
+    /// - `x: f32` - The x-coordinate of the pixel, in canvas rather than CSS pixels.
+    /// - `y: f32` - The y-coordinate of the pixel, in canvas rather than CSS pixels.
+    pub fn move_point(&mut self, index: usize, x: f32, y: f32) -> Option<usize> {
+        let to = self.chart_coords("Dist", x, y)?;
+        self.set_point(index, to[0], to[1])
+    }
+
+    /// Moves a point of the progress distribution, modifying other points as necessary to
+    /// preserve monotonicity. Returns the index of the point after moving it, or nothing if the
+    /// index is out of range or the coordinates aren't finite.
+    ///
+    /// Consecutive moves are undone together until `end_edit` is called.
+    ///
+    /// # Arguments
+    ///
+    /// - `index: usize` - The index of the point to move.
+    /// - `x: f32` - The new proportion of required insights that have been discovered.
+    /// - `y: f32` - The new probability of being no more than this much of the way done.
+    pub fn set_point(&mut self, index: usize, x: f32, y: f32) -> Option<usize> {
+        let before = self.snapshot();
+        let moved = dist::move_point(&mut self.prog_points, index, x, y)?;
+        self.hand_edited(Edit::Move, before);
+        Some(moved)
+    }
+
+    /// Replaces the progress distribution with the given points, adding points at zero and one if
+    /// they are missing. Returns whether the points were valid, i.e., within bounds and monotone.
+    ///
+    /// # Arguments
+    ///
+    /// - `xs: Vec<f32>` - The proportions of required insights that have been discovered.
+    /// - `ys: Vec<f32>` - The probabilities of being no more than this much of the way done.
+    pub fn set_points(&mut self, xs: Vec<f32>, ys: Vec<f32>) -> bool {
+        if xs.len() != ys.len() {
+            return false;
+        }
+
+        match dist::from_points(xs.into_iter().zip(ys).collect()) {
+            Some(pts) => {
+                let before = self.snapshot();
+                self.prog_points = pts;
+                self.hand_edited(Edit::Points, before);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Resets progress distribution to initial values.
     pub fn reset_progress(&mut self) {
+        let before = self.snapshot();
         self.prog_points = vec![(0., 0.), (1., 1.)];
+        self.hand_edited(Edit::Points, before);
         self.draw_dist();
         self.draw_timeline();
     }
 
+    /// Undoes the most recent edit to the progress distribution, preset priors, year range or
+    /// regression mode. Returns whether there was anything to undo.
+    pub fn undo(&mut self) -> bool {
+        let current = self.snapshot();
+        match self.history.undo(current) {
+            Some(before) => {
+                self.restore(before);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the most recently undone edit. Returns whether there was anything to redo.
+    pub fn redo(&mut self) -> bool {
+        let current = self.snapshot();
+        match self.history.redo(current) {
+            Some(after) => {
+                self.restore(after);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ends the drag of a point or of the year range in progress, so that the next edit is undone
+    /// separately.
+    pub fn end_edit(&mut self) {
+        self.history.close();
+    }
+
     /// Draws the progress distribution to a canvas.
-    pub fn draw_dist(&self) {
-        dist::draw_dist(&self.prog_points);
+    pub fn draw_dist(&mut self) {
+        self.dist_coords = dist::draw_dist(
+            &self.prog_points,
+            self.interp,
+            self.prog_band.as_deref(),
+            self.x_scale,
+            self.y_scale,
+        );
     }
 
     /// Sets the number of samples to take from the progression distribution.
-    pub fn set_num_samples(&mut self, n: u16) {
+    pub fn set_num_samples(&mut self, n: u32) {
         self.num_samples = n;
     }
 
+    /// Sets the seed for sampling from the progression distribution.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    /// Re-samples the mixture of preset priors with `n_seeds` consecutive seeds, starting from the
+    /// current seed, and returns the largest standard deviation across seeds at any point of the
+    /// progress distribution.
+    pub fn seed_spread(&self, n_seeds: u32) -> Option<f32> {
+        if self.components.is_empty() {
+            return None;
+        }
+
+        dist::seed_spread(
+            &self.components,
+            &self.sampler(),
+            n_seeds as u64,
+            self.insights_cnt as f32,
+        )
+    }
+
+    /// Sets the standard error to keep sampling until every point of the progress distribution
+    /// reaches. Non-positive values turn this off, so that a fixed number of samples is taken.
+    pub fn set_target_se(&mut self, target_se: f32) {
+        self.target_se = if target_se > 0. {
+            Some(target_se)
+        } else {
+            None
+        };
+    }
+
+    /// Sets where the randomness for sampling from the progression distribution comes from.
+    /// Returns whether the method was recognised; others are ignored.
+    ///
+    /// # Argument
+    ///
+    /// - `method: &str` - String representation of the method.
+    pub fn set_sample_method(&mut self, method: &str) -> bool {
+        self.method = match method {
+            "Random" => dist::SampleMethod::Random,
+            "Quasi" => dist::SampleMethod::Quasi,
+            _ => return false,
+        };
+        true
+    }
+
+    /// Sets how to interpolate between the points of the progress distribution. Returns whether
+    /// the interpolation was recognised; others are ignored.
+    ///
+    /// # Argument
+    ///
+    /// - `interp: &str` - String representation of the interpolation.
+    pub fn set_interp(&mut self, interp: &str) -> bool {
+        self.interp = match interp {
+            "Linear" => timeline::Interp::Linear,
+            "Pchip" => timeline::Interp::Pchip,
+            _ => return false,
+        };
+        true
+    }
+
+    /// Sets the scales of the axes of the progress distribution chart, which also apply to adding
+    /// and moving points on it. Returns whether both scales were recognised; otherwise neither is
+    /// changed.
+    ///
+    /// # Arguments
+    ///
+    /// - `x_scale: &str` - String representation of the scale of the proportion axis.
+    /// - `y_scale: &str` - String representation of the scale of the probability axis.
+    pub fn set_dist_scales(&mut self, x_scale: &str, y_scale: &str) -> bool {
+        match (parse_scale(x_scale), parse_scale(y_scale)) {
+            (Some(x_scale), Some(y_scale)) => {
+                self.x_scale = x_scale;
+                self.y_scale = y_scale;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets whether preset priors are computed exactly rather than sampled.
+    pub fn set_exact(&mut self, exact: bool) {
+        self.exact = exact;
+    }
+
+    /// The probability that a sample from the mixture of preset priors exceeds the current number
+    /// of insights, i.e., the acceptance rate of naive rejection sampling.
+    pub fn acceptance_rate(&self) -> Option<f32> {
+        if self.components.is_empty() {
+            None
+        } else {
+            Some(dist::acceptance(&self.components, self.insights_cnt as f32))
+        }
+    }
+
+    /// Sets whether preset priors are added to the mixture with weight `weight`, or replace it.
+    /// Non-positive weights make them replace it.
+    pub fn set_mix_weight(&mut self, weight: f32) {
+        self.mix_weight = if weight > 0. { Some(weight) } else { None };
+    }
+
+    /// Removes a component from the mixture of preset priors. The last component can't be removed,
+    /// use `reset_progress` instead.
+    pub fn remove_component(&mut self, index: usize) {
+        if index < self.components.len() && self.components.len() > 1 {
+            let mut components = self.components.clone();
+            components.remove(index);
+            self.set_components(components);
+        }
+    }
+
+    /// A description of the mixture of preset priors.
+    pub fn mixture_repr(&self) -> String {
+        let total: f32 = self.components.iter().map(|(w, _)| w).sum();
+
+        self.components
+            .iter()
+            .map(|(w, prior)| format!("{:.0}% {}", 100. * w / total, prior))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    /// Makes the minimum plausible number of insights of the Pareto priors uncertain, log-uniform
+    /// between the minimum passed to the preset and `upper`. Values of `upper` no larger than the
+    /// minimum make it certain again.
+    pub fn set_min_upper(&mut self, upper: u32) {
+        self.min_upper = if upper > 0 { Some(upper) } else { None };
+    }
+
     /// Sets the distribution to a Pareto with the chosen parameters.
     ///
     /// # Arguments
     /// - `min: u32` - The minimum plausible number of insights.
     /// - `q: f32` - The `q` parameter for the Pareto.
     pub fn set_pareto(&mut self, min: u32, q: f32) {
-        let pts = dist::pareto(min, self.num_samples as usize, dist::DistMode::Q(q));
-        self.prog_points = pts;
+        self.set_prior(Prior::Pareto(self.pareto_min(min), DistMode::Q(q)));
     }
 
     /// Sets the distribution to a mixture of Paretos where the `q` parameters are sampled from a
@@ -169,21 +747,109 @@ impl State {
     /// # Arguments
     /// - `min: u32` - The minimum plausible number of insights.
     pub fn set_pareto_uniform(&mut self, min: u32) {
-        let pts = dist::pareto(min, self.num_samples as usize, dist::DistMode::Uniform);
-        self.prog_points = pts;
+        self.set_prior(Prior::Pareto(self.pareto_min(min), DistMode::Uniform));
     }
 
     /// Sets the distribution to a mixture of Paretos where the `q` parameters are sampled from a
     /// Beta distribution parameterized by `alpha` and `beta`.
     pub fn set_pareto_beta(&mut self, min: u32, alpha: f32, beta: f32) {
-        let mode = dist::DistMode::Beta(alpha, beta);
-        let pts = dist::pareto(min, self.num_samples as usize, mode);
-        self.prog_points = pts;
+        self.set_prior(Prior::Pareto(
+            self.pareto_min(min),
+            DistMode::Beta(alpha, beta),
+        ));
+    }
+
+    /// Sets the distribution to a log-normal.
+    ///
+    /// # Arguments
+    /// - `median: f32` - The median number of required insights.
+    /// - `sigma: f32` - The standard deviation of the logarithm of the number of required insights.
+    pub fn set_lognormal(&mut self, median: f32, sigma: f32) {
+        self.set_prior(Prior::LogNormal(median, sigma));
+    }
+
+    /// Sets the distribution to a log-uniform.
+    ///
+    /// # Arguments
+    /// - `min: u32` - The minimum plausible number of insights.
+    /// - `max: u32` - The maximum plausible number of insights.
+    pub fn set_log_uniform(&mut self, min: u32, max: u32) {
+        self.set_prior(Prior::LogUniform(min, max));
+    }
+
+    /// Sets the distribution to a Gamma.
+    ///
+    /// # Arguments
+    /// - `shape: f32` - The shape parameter.
+    /// - `scale: f32` - The scale parameter.
+    pub fn set_gamma(&mut self, shape: f32, scale: f32) {
+        self.set_prior(Prior::Gamma(shape, scale));
+    }
+
+    /// Sets the distribution to a generalized Pareto.
+    ///
+    /// # Arguments
+    /// - `min: u32` - The minimum plausible number of insights, i.e., the location parameter.
+    /// - `shape: f32` - The shape parameter.
+    /// - `scale: f32` - The scale parameter.
+    pub fn set_gen_pareto(&mut self, min: u32, shape: f32, scale: f32) {
+        self.set_prior(Prior::GenPareto(min, shape, scale));
+    }
+
+    /// Fits a parametric distribution to quantiles of the progress distribution, and replaces the
+    /// progress distribution with the fit. Returns a description of the fit, or `None` if the
+    /// quantiles or the family are invalid.
+    ///
+    /// # Arguments
+    ///
+    /// - `portions: Vec<f32>` - Proportions of required insights that have been discovered.
+    /// - `probs: Vec<f32>` - The probability that the process is no more than each proportion of
+    /// the way done.
+    /// - `family: &str` - String representation of the family to fit.
+    pub fn fit_quantiles(
+        &mut self,
+        portions: Vec<f32>,
+        probs: Vec<f32>,
+        family: &str,
+    ) -> Option<String> {
+        let unit = |v: &f32| (0.0..=1.0).contains(v);
+        if portions.len() != probs.len() || !portions.iter().chain(&probs).all(unit) {
+            return None;
+        }
+
+        let pts: Vec<(f32, f32)> = portions.into_iter().zip(probs).collect();
+        let fit = fit::fit(&pts, parse_family(family)?)?;
+
+        let before = self.snapshot();
+        self.prog_points = fit.points();
+        self.hand_edited(Edit::Points, before);
+
+        Some(fit.to_string())
+    }
+
+    /// Fits a parametric distribution to the progress distribution, optionally replacing the
+    /// progress distribution with the fit. Returns a description of the fit, or `None` if the family
+    /// is invalid or the fit fails.
+    ///
+    /// # Arguments
+    ///
+    /// - `family: &str` - String representation of the family to fit.
+    /// - `replace: bool` - Whether to replace the progress distribution with the fit.
+    pub fn fit_points(&mut self, family: &str, replace: bool) -> Option<String> {
+        let fit = fit::fit_curve(&self.prog_points, self.interp, parse_family(family)?)?;
+
+        if replace {
+            let before = self.snapshot();
+            self.prog_points = fit.points();
+            self.hand_edited(Edit::Points, before);
+        }
+
+        Some(fit.to_string())
     }
 
     /// Draws the insights plot.
     pub fn draw_insights(&mut self) {
-        insights::draw_insights(
+        self.insights_coords = insights::draw_insights(
             self.year_min,
             self.year_max,
             &self.sub_cum,
@@ -192,13 +858,209 @@ impl State {
         );
     }
 
+    /// Draws the distribution of the total number of insights required, both as a cumulative
+    /// distribution that can be edited and as a histogram.
+    pub fn draw_required(&mut self) {
+        let insights_cnt = self.insights_cnt as f32;
+
+        self.required_coords =
+            required::draw_required(&self.prog_points, self.interp, insights_cnt);
+        required::draw_required_hist(&self.prog_points, self.interp, insights_cnt);
+    }
+
     /// Draws the tinmeline plot.
-    pub fn draw_timeline(&self) {
-        timeline::draw_timeline(self.last, &self.prog_points, &self.inv_curve);
+    ///
+    /// This also computes the implied timeline, which the statistics of the timeline below are
+    /// taken from until it is next drawn. They return nothing if it couldn't be computed. Like the
+    /// chart, they are conditioned on the required number of insights not having been reached by
+    /// the year the projection starts from.
+    pub fn draw_timeline(&mut self) {
+        self.timeline = self.compute_timeline();
+        self.timeline_coords = match &self.timeline {
+            Some(timeline) => match self.timeline_view {
+                timeline::TimelineView::Cumulative => {
+                    timeline::draw_timeline(self.now, self.last, timeline)
+                }
+                timeline::TimelineView::Density(width) => {
+                    timeline::draw_timeline_density(self.now, self.last, timeline, width)
+                }
+            },
+            None => None,
+        };
+    }
+
+    /// The first year by which the required number of insights has been reached with probability
+    /// `q`, e.g., the median for `q = 0.5`. Returns nothing if that doesn't happen within the
+    /// timeline.
+    pub fn timeline_quantile(&self, q: f32) -> Option<f32> {
+        self.timeline.as_ref()?.quantile(q)
+    }
+
+    /// The implied timeline, as fractional years and the probability that the required number of
+    /// insights has been reached by then, flattened into `[year0, prob0, year1, prob1, ...]`.
+    pub fn timeline_points(&self) -> Option<Vec<f32>> {
+        let timeline = self.timeline.as_ref()?;
+        Some(
+            timeline
+                .points()
+                .iter()
+                .flat_map(|&(year, prob)| vec![year, prob])
+                .collect(),
+        )
+    }
+
+    /// The mean year the required number of insights is reached, given that it is reached by the
+    /// last year of the timeline.
+    pub fn timeline_mean(&self) -> Option<f32> {
+        self.timeline.as_ref()?.mean()
+    }
+
+    /// The probability that the required number of insights is reached, but only after the last
+    /// year of the timeline.
+    pub fn timeline_beyond(&self) -> Option<f32> {
+        Some(self.timeline.as_ref()?.beyond())
+    }
+
+    /// The probability that the required number of insights has been reached by `year`.
+    pub fn prob_by(&self, year: f32) -> Option<f32> {
+        Some(self.timeline.as_ref()?.prob_by(year))
+    }
+
+    /// The probability that the required number of insights is never reached under the fit curve.
+    /// Returns nothing unless the fit curve levels off, since the others reach every count.
+    pub fn never_prob(&self) -> Option<f32> {
+        if !self.mode.saturates() {
+            return None;
+        }
+
+        Some(self.timeline.as_ref()?.never())
     }
 }
 
 impl State {
+    /// Computes the timeline implied by the progress distribution and the fit curve.
+    fn compute_timeline(&self) -> Option<timeline::Timeline> {
+        let insights_cnt = self.insights_cnt as f32;
+        timeline::Timeline::new(
+            &self.prog_points,
+            self.interp,
+            self.inv_curve.as_ref()?,
+            insights_cnt,
+            self.mode.saturates(),
+            self.now as f32,
+        )
+    }
+
+    /// The minimum plausible number of insights of a Pareto prior.
+    fn pareto_min(&self, min: u32) -> Min {
+        match self.min_upper {
+            Some(upper) if upper > min => Min::LogUniform(min, upper),
+            _ => Min::Fixed(min),
+        }
+    }
+
+    /// The settings for sampling from the prior distribution.
+    fn sampler(&self) -> dist::Sampler {
+        dist::Sampler {
+            n_samps: self.num_samples as usize,
+            seed: self.seed as u64,
+            target_se: self.target_se,
+            method: self.method,
+        }
+    }
+
+    /// Sets the distribution to a preset prior, or adds it to the mixture of preset priors.
+    fn set_prior(&mut self, prior: Prior) {
+        let components = match self.mix_weight {
+            Some(weight) => {
+                let mut components = self.components.clone();
+                components.push((weight, prior));
+                components
+            }
+            None => vec![(1., prior)],
+        };
+
+        self.set_components(components);
+    }
+
+    /// Sets the distribution to a mixture of preset priors, either computed exactly or sampled.
+    /// Invalid mixtures leave the distribution unchanged.
+    fn set_components(&mut self, components: Vec<(f32, Prior)>) {
+        let before = self.snapshot();
+        if self.apply_components(&components) {
+            self.components = components;
+            self.history.record(Edit::Prior, before);
+        }
+    }
+
+    /// Replaces the progress distribution with the one implied by a mixture of preset priors,
+    /// either computed exactly or sampled. Returns whether the mixture was valid; invalid mixtures
+    /// leave the distribution unchanged.
+    fn apply_components(&mut self, components: &[(f32, Prior)]) -> bool {
+        let est = if self.exact {
+            dist::exact_prior(components, self.insights_cnt as f32).map(|pts| (pts, None))
+        } else {
+            dist::sample_prior(components, &self.sampler(), self.insights_cnt as f32).map(|est| {
+                let band = est.band();
+                (est.pts, Some(band))
+            })
+        };
+
+        match est {
+            Some((pts, band)) => {
+                self.prog_points = pts;
+                self.prog_band = band;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finishes an edit of the progress distribution made by hand rather than by preset priors,
+    /// which leaves it without an error band or mixture, and records it in the history.
+    ///
+    /// # Arguments
+    ///
+    /// - `edit: Edit` - The kind of the edit.
+    /// - `before: Snapshot` - A snapshot from before the edit.
+    fn hand_edited(&mut self, edit: Edit, before: Snapshot) {
+        self.prog_band = None;
+        self.components.clear();
+        self.history.record(edit, before);
+    }
+
+    /// Takes a snapshot of the parts of the state that can be edited.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            prog_points: self.prog_points.clone(),
+            prog_band: self.prog_band.clone(),
+            components: self.components.clone(),
+            year_min: self.year_min,
+            year_max: self.year_max,
+            mode: self.mode,
+            insights_cnt: self.insights_cnt,
+        }
+    }
+
+    /// Goes back to a snapshot, refitting the curve if the year range or regression mode changed.
+    fn restore(&mut self, snapshot: Snapshot) {
+        let refit = (snapshot.year_min, snapshot.year_max, snapshot.mode)
+            != (self.year_min, self.year_max, self.mode);
+
+        self.prog_points = snapshot.prog_points;
+        self.prog_band = snapshot.prog_band;
+        self.components = snapshot.components;
+        self.year_min = snapshot.year_min;
+        self.year_max = snapshot.year_max;
+        self.mode = snapshot.mode;
+        self.insights_cnt = snapshot.insights_cnt;
+
+        if refit {
+            self.sub_cum = make_sub_cum(self.year_min, self.year_max);
+            self.set_curve();
+        }
+    }
+
     /// Updates the curve.
     fn set_curve(&mut self) {
         match insights::reg(&self.mode, &self.sub_cum) {
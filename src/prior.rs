@@ -0,0 +1,217 @@
+use crate::util::{gamma_inc_upper, ln_gamma, normal_quantile, normal_sf};
+use std::fmt;
+
+/// Different types of provided distributions
+#[derive(Clone, Copy)]
+pub enum DistMode {
+    /// Mixture of Paretos where the `q` parameter is sampled uniformly from [0.001, 1)
+    Uniform,
+    /// A single Pareto where the `q` parameter is provided by the user.
+    Q(f32),
+    /// Mixture of Paretos where the `q` parameter is sampled from a Beta.
+    Beta(f32, f32),
+}
+
+impl DistMode {
+    /// The probability that at least `reqs` insights are required, before conditioning on the
+    /// insights discovered so far.
+    ///
+    /// A single Pareto requires at least `min * 2^a` insights with probability `(1 - q)^a`, so a
+    /// mixture does so with probability `E[(1 - q)^a]`, which has a closed form for both the uniform
+    /// and Beta mixtures.
+    ///
+    /// # Arguments
+    ///
+    /// - `min: f64` - Plausible minimum number of insights to achieve AGI.
+    /// - `reqs: f64` - The number of required insights.
+    pub(crate) fn survival(self, min: f64, reqs: f64) -> f64 {
+        let a = (reqs / min).log2().max(0.);
+
+        match self {
+            DistMode::Uniform => 0.999f64.powf(a) / (a + 1.),
+            DistMode::Q(q) => (1. - q as f64).powf(a),
+            DistMode::Beta(alpha, beta) => {
+                let (alpha, beta) = (alpha as f64, beta as f64);
+                (ln_gamma(beta + a) + ln_gamma(alpha + beta)
+                    - ln_gamma(alpha + beta + a)
+                    - ln_gamma(beta))
+                .exp()
+            }
+        }
+    }
+
+    /// Whether the parameters describe a distribution.
+    fn is_valid(self) -> bool {
+        match self {
+            DistMode::Uniform => true,
+            DistMode::Q(q) => 0. < q && q < 1.,
+            DistMode::Beta(alpha, beta) => alpha > 0. && beta > 0.,
+        }
+    }
+}
+
+/// The number of nodes used to integrate over an uncertain minimum.
+const MIN_NODES: usize = 256;
+
+/// The plausible minimum number of insights of a Pareto.
+#[derive(Clone, Copy)]
+pub enum Min {
+    /// A single value.
+    Fixed(u32),
+    /// Log-uniform between the two bounds.
+    LogUniform(u32, u32),
+}
+
+impl Min {
+    /// Discretizes the distribution of the minimum into `(min, weight)` pairs. A log-uniform
+    /// minimum is split into equally likely intervals represented by their (log-scale) midpoints.
+    pub fn nodes(self) -> Vec<(f64, f64)> {
+        match self {
+            Min::Fixed(min) => vec![(min as f64, 1.)],
+            Min::LogUniform(lo, hi) => {
+                let (lo, hi) = ((lo as f64).ln(), (hi as f64).ln());
+                let width = (hi - lo) / MIN_NODES as f64;
+
+                (0..MIN_NODES)
+                    .map(|i| {
+                        let mid = lo + (i as f64 + 0.5) * width;
+                        (mid.exp(), 1. / MIN_NODES as f64)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Whether the parameters describe a distribution.
+    fn is_valid(self) -> bool {
+        match self {
+            Min::Fixed(min) => min > 0,
+            Min::LogUniform(lo, hi) => 0 < lo && lo < hi,
+        }
+    }
+}
+
+/// A prior distribution over the total number of insights required to achieve AGI.
+#[derive(Clone, Copy)]
+pub enum Prior {
+    /// A Pareto or mixture of Paretos, with the plausible minimum number of insights.
+    Pareto(Min, DistMode),
+    /// Log-normal, with the median and the standard deviation of the logarithm.
+    LogNormal(f32, f32),
+    /// Log-uniform, between the minimum and maximum number of insights.
+    LogUniform(u32, u32),
+    /// Gamma, with the shape and scale.
+    Gamma(f32, f32),
+    /// Generalized Pareto, with the location (i.e., minimum number of insights), shape and scale.
+    GenPareto(u32, f32, f32),
+}
+
+impl Prior {
+    /// Whether the parameters describe a distribution.
+    pub fn is_valid(self) -> bool {
+        match self {
+            Prior::Pareto(min, mode) => min.is_valid() && mode.is_valid(),
+            Prior::LogNormal(median, sigma) => median > 0. && sigma > 0.,
+            Prior::LogUniform(min, max) => 0 < min && min < max,
+            Prior::Gamma(shape, scale) => shape > 0. && scale > 0.,
+            Prior::GenPareto(_, _, scale) => scale > 0.,
+        }
+    }
+
+    /// The probability that at least `reqs` insights are required, before conditioning on the
+    /// insights discovered so far.
+    pub fn survival(self, reqs: f64) -> f64 {
+        match self {
+            Prior::Pareto(min, mode) => min
+                .nodes()
+                .into_iter()
+                .map(|(min, weight)| weight * mode.survival(min, reqs))
+                .sum(),
+            Prior::LogNormal(median, sigma) => {
+                normal_sf((reqs.ln() - (median as f64).ln()) / sigma as f64)
+            }
+            Prior::LogUniform(min, max) => {
+                let (lo, hi) = ((min as f64).ln(), (max as f64).ln());
+                ((hi - reqs.ln()) / (hi - lo)).clamp(0., 1.)
+            }
+            Prior::Gamma(shape, scale) => gamma_inc_upper(shape as f64, reqs / scale as f64),
+            Prior::GenPareto(loc, shape, scale) => {
+                let z = ((reqs - loc as f64) / scale as f64).max(0.);
+                if shape == 0. {
+                    (-z).exp()
+                } else {
+                    (1. + shape as f64 * z).max(0.).powf(-1. / shape as f64)
+                }
+            }
+        }
+    }
+
+    /// Inverts `survival`, i.e., finds the number of insights that are required with probability
+    /// `prob`.
+    pub fn inv_survival(self, prob: f64) -> f64 {
+        match self {
+            Prior::LogNormal(median, sigma) => {
+                median as f64 * (-sigma as f64 * normal_quantile(prob)).exp()
+            }
+            Prior::LogUniform(min, max) => {
+                let (lo, hi) = ((min as f64).ln(), (max as f64).ln());
+                (hi - prob * (hi - lo)).exp()
+            }
+            Prior::GenPareto(loc, shape, scale) => {
+                let (shape, scale) = (shape as f64, scale as f64);
+                if shape == 0. {
+                    loc as f64 - scale * prob.ln()
+                } else {
+                    loc as f64 + scale * (prob.powf(-shape) - 1.) / shape
+                }
+            }
+            // Bisect over the logarithm of the number of insights, since there is no closed form.
+            Prior::Pareto(..) | Prior::Gamma(..) => {
+                let (mut lo, mut hi) = (-20f64, 700f64);
+                for _ in 0..100 {
+                    let mid = 0.5 * (lo + hi);
+                    if self.survival(mid.exp()) > prob {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+
+                (0.5 * (lo + hi)).exp()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Min {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Min::Fixed(min) => write!(f, "min {}", min),
+            Min::LogUniform(lo, hi) => write!(f, "min {}-{}", lo, hi),
+        }
+    }
+}
+
+impl fmt::Display for Prior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Prior::Pareto(min, DistMode::Q(q)) => write!(f, "Pareto({}, q = {})", min, q),
+            Prior::Pareto(min, DistMode::Uniform) => {
+                write!(f, "Pareto({}, q ~ Uniform(0, 1))", min)
+            }
+            Prior::Pareto(min, DistMode::Beta(alpha, beta)) => {
+                write!(f, "Pareto({}, q ~ Beta({}, {}))", min, alpha, beta)
+            }
+            Prior::LogNormal(median, sigma) => {
+                write!(f, "LogNormal(median {}, sigma {})", median, sigma)
+            }
+            Prior::LogUniform(min, max) => write!(f, "LogUniform({}, {})", min, max),
+            Prior::Gamma(shape, scale) => write!(f, "Gamma(shape {}, scale {})", shape, scale),
+            Prior::GenPareto(min, shape, scale) => write!(
+                f,
+                "GenPareto(min {}, shape {}, scale {})",
+                min, shape, scale
+            ),
+        }
+    }
+}
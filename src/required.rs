@@ -0,0 +1,226 @@
+use crate::{
+    timeline::{prob_from_portion, Interp},
+    util::bisect,
+    CoordFn,
+};
+use itertools_num::linspace;
+use plotters::prelude::*;
+
+/// How many orders of magnitude beyond the number of insights discovered so far the charts of the
+/// required number of insights extend.
+const DECADES: f32 = 3.;
+
+/// The number of histogram bins per order of magnitude.
+const BINS_PER_DECADE: usize = 10;
+
+/// The quantiles of the total number of insights required marked on the histogram.
+const MARKED_QUANTILES: [f32; 3] = [0.1, 0.5, 0.9];
+
+/// Converts a point of the distribution of the total number of insights required to a point of
+/// the progress distribution.
+///
+/// # Arguments
+///
+/// - `reqs: f32` - The total number of insights required.
+/// - `prob: f32` - The probability that no more than `reqs` insights are required.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub(crate) fn to_progress(reqs: f32, prob: f32, insights_cnt: f32) -> (f32, f32) {
+    (insights_cnt / reqs, 1. - prob)
+}
+
+/// Calculates the probability that no more than `reqs` insights are required in total.
+///
+/// # Arguments
+///
+/// - `reqs: f32` - The total number of insights required.
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub(crate) fn required_cdf(
+    reqs: f32,
+    pts: &[(f32, f32)],
+    interp: Interp,
+    insights_cnt: f32,
+) -> Option<f32> {
+    Some(1. - prob_from_portion(insights_cnt / reqs, pts, interp)?)
+}
+
+/// Calculates a quantile of the total number of insights required. Returns nothing if the
+/// quantile is infinite, i.e., if the probability that the insights are never all discovered is
+/// at least `1 - q`.
+///
+/// # Arguments
+///
+/// - `q: f32` - The probability that no more than the quantile is required.
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub(crate) fn required_quantile(
+    q: f32,
+    pts: &[(f32, f32)],
+    interp: Interp,
+    insights_cnt: f32,
+) -> Option<f32> {
+    // The quantile is `insights_cnt / portion` for the smallest portion whose probability is at
+    // least `1 - q`.
+    let target = 1. - q;
+    if prob_from_portion(0., pts, interp)? >= target {
+        return None;
+    }
+
+    let (_, hi) = bisect(
+        |portion| matches!(prob_from_portion(portion, pts, interp), Some(prob) if prob < target),
+        0.,
+        1.,
+    );
+
+    Some(insights_cnt / hi)
+}
+
+/// Draws a histogram of the total number of insights required, with log-spaced bins and the
+/// quantiles in `MARKED_QUANTILES` marked.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub(crate) fn draw_required_hist(
+    pts: &[(f32, f32)],
+    interp: Interp,
+    insights_cnt: f32,
+) -> Option<()> {
+    let lo = insights_cnt.log10();
+    let hi = lo + DECADES;
+    let n_bins = BINS_PER_DECADE * DECADES as usize;
+
+    let edges = linspace(lo, hi, n_bins + 1)
+        .map(|lx| Some((lx, required_cdf(10f32.powf(lx), pts, interp, insights_cnt)?)))
+        .collect::<Option<Vec<(f32, f32)>>>()?;
+    let bins: Vec<(f32, f32, f32)> = edges
+        .windows(2)
+        .map(|w| (w[0].0, w[1].0, (w[1].1 - w[0].1).max(0.)))
+        .collect();
+
+    let beyond = 1. - edges.last()?.1;
+    let max_mass = bins.iter().map(|&(_, _, mass)| mass).fold(0f32, f32::max);
+
+    let backend = CanvasBackend::new("required_hist")?;
+    let root = backend.into_drawing_area();
+    let font: FontDesc = ("Arial", 20.0).into();
+
+    root.fill(&WHITE).ok()?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            format!(
+                "Pr(more than {:.0} required) = {:.1}%",
+                10f32.powf(hi),
+                100. * beyond
+            ),
+            font,
+        )
+        .x_label_area_size(50)
+        .y_label_area_size(50)
+        .build_ranged(lo..hi, 0f32..(1.1 * max_mass).max(0.01))
+        .ok()?;
+
+    chart
+        .configure_mesh()
+        .x_label_formatter(&|lx| format!("{:.0}", 10f32.powf(*lx)))
+        .x_desc("Total number of insights required (log scale)")
+        .y_desc("Probability")
+        .draw()
+        .ok()?;
+
+    chart
+        .draw_series(bins.iter().map(|&(a, b, mass)| {
+            Rectangle::new(
+                [(a, 0.), (b, mass)],
+                RGBColor(0, 136, 238).mix(0.6).filled(),
+            )
+        }))
+        .ok()?;
+
+    let mut marked = false;
+    for &q in MARKED_QUANTILES.iter() {
+        let reqs = match required_quantile(q, pts, interp, insights_cnt) {
+            Some(reqs) if reqs.log10() <= hi => reqs,
+            _ => continue,
+        };
+
+        let lx = reqs.log10();
+        chart
+            .draw_series(LineSeries::new(
+                vec![(lx, 0.), (lx, 1.1 * max_mass)],
+                &RGBColor(238, 85, 0),
+            ))
+            .ok()?
+            .label(format!("{:.0}%: {:.0}", 100. * q, reqs))
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RGBColor(238, 85, 0)));
+        marked = true;
+    }
+
+    if marked {
+        chart
+            .configure_series_labels()
+            .border_style(&BLACK)
+            .draw()
+            .ok()?;
+    }
+
+    Some(())
+}
+
+/// Draws the distribution of the total number of insights required on a log axis, which is the
+/// progress distribution seen from the other side.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+///
+/// Returns the map from pixels on the canvas to the number of insights and the probability.
+pub(crate) fn draw_required(
+    pts: &[(f32, f32)],
+    interp: Interp,
+    insights_cnt: f32,
+) -> Option<CoordFn> {
+    let lo = insights_cnt.log10();
+    let hi = lo + DECADES;
+
+    let cdf = linspace(lo, hi, 500)
+        .map(|lx| Some((lx, required_cdf(10f32.powf(lx), pts, interp, insights_cnt)?)))
+        .collect::<Option<Vec<(f32, f32)>>>()?;
+
+    let backend = CanvasBackend::new("required_dist")?;
+    let root = backend.into_drawing_area();
+    let font: FontDesc = ("Arial", 20.0).into();
+
+    root.fill(&WHITE).ok()?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Or draw the total number of insights required", font)
+        .x_label_area_size(50)
+        .y_label_area_size(50)
+        .build_ranged(lo..hi, 0f32..1f32)
+        .ok()?;
+
+    chart
+        .configure_mesh()
+        .x_label_formatter(&|lx| format!("{:.0}", 10f32.powf(*lx)))
+        .x_desc("Total number of insights required (log scale)")
+        .y_desc("Pr(no more than this many)")
+        .draw()
+        .ok()?;
+
+    chart
+        .draw_series(LineSeries::new(cdf, &RGBColor(0, 136, 238)))
+        .ok()?;
+
+    let trans = chart.into_coord_trans();
+    Some(Box::new(move |pixel| {
+        trans(pixel).map(|(lx, prob)| (10f32.powf(lx), prob))
+    }))
+}
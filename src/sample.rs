@@ -1,6 +1,337 @@
-use rand::distributions::Exp;
+use crate::{
+    prior::{DistMode, Prior},
+    util::beta_inc_inv,
+};
+use rand::Rng;
+use rand_distr::{Beta, Exp1};
+use std::cmp::Ordering;
 
-fn sample_pareto(min: f64, q: f64) {
-    let lambda = -(1. - q).ln();
-    let n_doublings = Exp.
+/// How the `q` parameter of a Pareto is drawn once conditioned on the insights discovered so far.
+enum QDist {
+    /// A single `q` provided by the user.
+    Fixed(f32),
+    /// `q` is uniform on [0.001, 1) a priori, so `1 - q` has a density proportional to
+    /// `(1 - q)^shift` a posteriori, which can be sampled by inversion.
+    Uniform,
+    /// A Beta a priori remains a Beta a posteriori, with the given parameters.
+    Beta(Beta<f64>, f64, f64),
+}
+
+/// Samples the number of insights required from a Pareto or mixture of Paretos, conditioned on
+/// exceeding the number of insights discovered so far.
+///
+/// Each Pareto requires `min * 2^x` insights where the number of doublings `x` is exponential.
+/// Exceeding `cnt` insights means that `x > log2(cnt / min)`, and since the exponential is
+/// memoryless, `x` is then just `log2(cnt / min)` plus another exponential. Rather than rejecting
+/// samples that fall short of `cnt`, this draws from the conditional distribution directly, so the
+/// cost of a sample doesn't depend on how often the original would have been rejected.
+pub(crate) struct TruncPareto {
+    /// Plausible minimum number of insights to achieve AGI.
+    min: f32,
+    /// The number of doublings needed to exceed the insights discovered so far.
+    shift: f64,
+    /// How to draw `q`.
+    q: QDist,
+}
+
+impl TruncPareto {
+    /// Sets up a sampler for a Pareto or mixture of Paretos.
+    ///
+    /// # Arguments
+    ///
+    /// - `min: f64` - Plausible minimum number of insights to achieve AGI.
+    /// - `cnt: f32` - The number of insights discovered so far.
+    /// - `mode: DistMode` - Which distribution to sample from.
+    pub(crate) fn new(min: f64, cnt: f32, mode: DistMode) -> Option<TruncPareto> {
+        let shift = (cnt as f64 / min).log2().max(0.);
+
+        let q = match mode {
+            DistMode::Q(q) => QDist::Fixed(q),
+            DistMode::Uniform => QDist::Uniform,
+            DistMode::Beta(alpha, beta) => {
+                let (alpha, beta) = (alpha as f64, beta as f64 + shift);
+                QDist::Beta(Beta::new(alpha, beta).ok()?, alpha, beta)
+            }
+        };
+
+        Some(TruncPareto {
+            min: min as f32,
+            shift,
+            q,
+        })
+    }
+
+    /// Draws `q` for the uniform mixture by inversion.
+    fn uniform_q(&self, u: f64) -> f64 {
+        1. - 0.999 * u.powf(1. / (self.shift + 1.))
+    }
+
+    /// Computes the number of required insights from `q` and a standard exponential.
+    fn required(&self, q: f64, exp: f64) -> f32 {
+        let lambda = -(1. - q).ln();
+        let x = self.shift + exp / lambda;
+
+        // prevent overflow
+        self.min * 2f32.powf(x.min(800.) as f32)
+    }
+
+    /// Draws a sample.
+    ///
+    /// # Arguments
+    ///
+    /// - `q_rng: &mut R1` - Random number generator used for drawing `q`.
+    /// - `x_rng: &mut R2` - Random number generator used for drawing the number of doublings.
+    pub(crate) fn sample<R1: Rng, R2: Rng>(&self, q_rng: &mut R1, x_rng: &mut R2) -> f32 {
+        let q = match &self.q {
+            QDist::Fixed(q) => *q as f64,
+            QDist::Uniform => self.uniform_q(q_rng.gen()),
+            QDist::Beta(dist, _, _) => q_rng.sample(dist),
+        };
+
+        self.required(q, x_rng.sample(Exp1))
+    }
+
+    /// Maps a point of the unit square to a sample by inverting the distribution functions of `q`
+    /// and the number of doublings.
+    ///
+    /// # Arguments
+    ///
+    /// - `u_q: f64` - Uniform on [0, 1), used for drawing `q`.
+    /// - `u_x: f64` - Uniform on [0, 1), used for drawing the number of doublings.
+    pub(crate) fn quantile(&self, u_q: f64, u_x: f64) -> f32 {
+        let q = match &self.q {
+            QDist::Fixed(q) => *q as f64,
+            QDist::Uniform => self.uniform_q(u_q),
+            QDist::Beta(_, alpha, beta) => beta_inc_inv(*alpha, *beta, u_q),
+        };
+
+        self.required(q, -(1. - u_x).ln())
+    }
+}
+
+/// Picks the item corresponding to a uniform draw from a collection of items paired with their
+/// cumulative probabilities.
+fn pick<T>(items: &[(f64, T)], u: f64) -> &T {
+    let idx = match items.binary_search_by(|(cum, _)| {
+        if *cum <= u {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    }) {
+        Ok(idx) | Err(idx) => idx,
+    };
+
+    &items[idx.min(items.len() - 1)].1
+}
+
+/// Pairs items with their cumulative probabilities, given unnormalized weights.
+fn cumulative<T, I: IntoIterator<Item = (f64, T)>>(weighted: I) -> Vec<(f64, T)> {
+    let weighted: Vec<(f64, T)> = weighted.into_iter().collect();
+    let total: f64 = weighted.iter().map(|(w, _)| w).sum();
+
+    weighted
+        .into_iter()
+        .scan(0., |cum, (w, item)| {
+            *cum += w / total;
+            Some((*cum, item))
+        })
+        .collect()
+}
+
+/// Samples from a prior conditioned on exceeding the number of insights discovered so far.
+pub(crate) enum TruncPrior {
+    /// Paretos and mixtures of Paretos have a dedicated sampler for each node of the minimum,
+    /// paired with the cumulative probability of the nodes up to and including it after
+    /// conditioning.
+    Pareto(Vec<(f64, TruncPareto)>),
+    /// Everything else is sampled by inverting its survival function, restricted to the
+    /// probability of exceeding the insights discovered so far.
+    Inverse(Prior, f64),
+}
+
+impl TruncPrior {
+    /// Sets up a sampler for a prior.
+    ///
+    /// Returns `None` if the parameters of the prior are invalid, or if it rules out exceeding
+    /// the number of insights discovered so far.
+    ///
+    /// # Arguments
+    ///
+    /// - `prior: Prior` - The prior.
+    /// - `cnt: f32` - The number of insights discovered so far.
+    pub(crate) fn new(prior: Prior, cnt: f32) -> Option<TruncPrior> {
+        if !prior.is_valid() {
+            return None;
+        }
+
+        match prior {
+            Prior::Pareto(min, mode) => {
+                // The minimum is updated on the insights discovered so far as well.
+                let nodes = min
+                    .nodes()
+                    .into_iter()
+                    .map(|(min, weight)| {
+                        let weight = weight * mode.survival(min, cnt as f64);
+                        Some((weight, TruncPareto::new(min, cnt, mode)?))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(TruncPrior::Pareto(cumulative(nodes)))
+            }
+            _ => {
+                let evidence = prior.survival(cnt as f64);
+                if evidence > 0. {
+                    Some(TruncPrior::Inverse(prior, evidence))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Draws a sample.
+    ///
+    /// # Arguments
+    ///
+    /// - `q_rng: &mut R1` - Random number generator used for the minimum and `q` of Paretos.
+    /// - `x_rng: &mut R2` - Random number generator used for everything else.
+    pub(crate) fn sample<R1: Rng, R2: Rng>(&self, q_rng: &mut R1, x_rng: &mut R2) -> f32 {
+        match self {
+            TruncPrior::Pareto(nodes) if nodes.len() == 1 => nodes[0].1.sample(q_rng, x_rng),
+            TruncPrior::Pareto(nodes) => pick(nodes, q_rng.gen()).sample(q_rng, x_rng),
+            TruncPrior::Inverse(prior, evidence) => {
+                prior.inv_survival(evidence * (1. - x_rng.gen::<f64>())) as f32
+            }
+        }
+    }
+
+    /// Maps a point of the unit cube to a sample.
+    ///
+    /// # Arguments
+    ///
+    /// - `u_q: f64` - Uniform on [0, 1), used for drawing `q` for Paretos.
+    /// - `u_x: f64` - Uniform on [0, 1), used for everything else.
+    /// - `u_min: f64` - Uniform on [0, 1), used for drawing the minimum for Paretos.
+    pub(crate) fn quantile(&self, u_q: f64, u_x: f64, u_min: f64) -> f32 {
+        match self {
+            TruncPrior::Pareto(nodes) => pick(nodes, u_min).quantile(u_q, u_x),
+            TruncPrior::Inverse(prior, evidence) => {
+                prior.inv_survival(evidence * (1. - u_x)) as f32
+            }
+        }
+    }
+}
+
+/// Samples from a mixture of priors conditioned on exceeding the number of insights discovered so
+/// far, as a sampler for each component paired with the cumulative probability of the components
+/// up to and including it after conditioning.
+pub(crate) struct TruncMixture(Vec<(f64, TruncPrior)>);
+
+impl TruncMixture {
+    /// Sets up a sampler for a mixture of priors.
+    ///
+    /// Returns `None` if the parameters of any component are invalid, if any weight isn't
+    /// positive, or if the mixture rules out exceeding the number of insights discovered so far.
+    ///
+    /// # Arguments
+    ///
+    /// - `components: &[(f32, Prior)]` - The weight and prior of each component.
+    /// - `cnt: f32` - The number of insights discovered so far.
+    pub(crate) fn new(components: &[(f32, Prior)], cnt: f32) -> Option<TruncMixture> {
+        let comps = components
+            .iter()
+            .map(|&(weight, prior)| {
+                if weight <= 0. || !prior.is_valid() {
+                    return None;
+                }
+
+                // Components that rule out exceeding the insights discovered so far are dropped.
+                let evidence = weight as f64 * prior.survival(cnt as f64);
+                Some(TruncPrior::new(prior, cnt).map(|dist| (evidence, dist)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let comps: Vec<_> = comps.into_iter().flatten().collect();
+        if comps.is_empty() {
+            return None;
+        }
+
+        Some(TruncMixture(cumulative(comps)))
+    }
+
+    /// Draws a sample.
+    ///
+    /// # Arguments
+    ///
+    /// - `q_rng: &mut R1` - Random number generator used for the minimum and `q` of Paretos.
+    /// - `x_rng: &mut R2` - Random number generator used for everything else.
+    /// - `c_rng: &mut R3` - Random number generator used for picking components.
+    pub(crate) fn sample<R1: Rng, R2: Rng, R3: Rng>(
+        &self,
+        q_rng: &mut R1,
+        x_rng: &mut R2,
+        c_rng: &mut R3,
+    ) -> f32 {
+        match self.0.as_slice() {
+            [(_, dist)] => dist.sample(q_rng, x_rng),
+            comps => pick(comps, c_rng.gen()).sample(q_rng, x_rng),
+        }
+    }
+
+    /// Maps a point of the unit hypercube to a sample. The coordinates are used for drawing `q`
+    /// for Paretos, everything else, the minimum for Paretos, and the component, in that order.
+    pub(crate) fn quantile(&self, u: [f64; 4]) -> f32 {
+        pick(&self.0, u[3]).quantile(u[0], u[1], u[2])
+    }
+}
+
+/// Four-dimensional Halton sequence (https://en.wikipedia.org/wiki/Halton_sequence), with a
+/// random shift modulo one so that different seeds give different, but equally well spread, points.
+pub(crate) struct Halton {
+    /// Index of the next point.
+    index: u64,
+    /// Random shift applied to each coordinate.
+    shift: [f64; 4],
+}
+
+/// The base of the van der Corput sequence for each coordinate of the Halton sequence.
+const BASES: [u64; 4] = [2, 3, 5, 7];
+
+impl Halton {
+    /// Starts a Halton sequence with a shift drawn from `rng`.
+    pub(crate) fn new<R: Rng>(rng: &mut R) -> Halton {
+        Halton {
+            index: 1,
+            shift: rng.gen(),
+        }
+    }
+}
+
+/// Computes the `index`th element of the van der Corput sequence in `base`.
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let mut inv = 0.;
+    let mut scale = 1. / base as f64;
+
+    while index > 0 {
+        inv += (index % base) as f64 * scale;
+        index /= base;
+        scale /= base as f64;
+    }
+
+    inv
+}
+
+impl Iterator for Halton {
+    type Item = [f64; 4];
+
+    fn next(&mut self) -> Option<[f64; 4]> {
+        let mut point = [0.; 4];
+        for (i, u) in point.iter_mut().enumerate() {
+            *u = (radical_inverse(self.index, BASES[i]) + self.shift[i]).fract();
+        }
+        self.index += 1;
+
+        Some(point)
+    }
 }
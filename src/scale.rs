@@ -0,0 +1,63 @@
+use std::ops::Range;
+
+/// The smallest distance from zero (and, for logit axes, from one) shown on log and logit axes,
+/// which can't reach them.
+const MIN_SHOWN: f32 = 1e-3;
+
+/// Scales for the axes of the progress distribution chart, whose values lie between zero and one.
+#[derive(Clone, Copy)]
+pub enum Scale {
+    Linear,
+    /// Base 10 logarithm, which spreads out values near zero.
+    Log,
+    /// Base 10 log-odds, which spreads out values near both zero and one.
+    Logit,
+}
+
+impl Scale {
+    /// The range of the axis, in scaled units.
+    pub fn range(self) -> Range<f32> {
+        self.scaled(0.)..self.scaled(1.)
+    }
+
+    /// Converts a value to scaled units, clamping values that can't be shown to the ends of the
+    /// axis.
+    pub fn scaled(self, v: f32) -> f32 {
+        match self {
+            Scale::Linear => v,
+            Scale::Log => v.clamp(MIN_SHOWN, 1.).log10(),
+            Scale::Logit => {
+                let v = v.clamp(MIN_SHOWN, 1. - MIN_SHOWN);
+                (v / (1. - v)).log10()
+            }
+        }
+    }
+
+    /// Converts a value in scaled units back to a value between zero and one.
+    pub fn unscaled(self, u: f32) -> f32 {
+        match self {
+            Scale::Linear => u,
+            Scale::Log => 10f32.powf(u),
+            Scale::Logit => 1. / (1. + 10f32.powf(-u)),
+        }
+    }
+
+    /// Converts a value to the proportion of the way along the axis it is shown at.
+    pub fn to_unit(self, v: f32) -> f32 {
+        let Range { start, end } = self.range();
+        (self.scaled(v) - start) / (end - start)
+    }
+
+    /// Labels a tick of the axis, given in scaled units, with the value it shows, using enough
+    /// decimal places to tell values near zero and one apart.
+    pub fn label(self, u: f32) -> String {
+        let v = self.unscaled(u);
+        let places = match self {
+            Scale::Linear => 1,
+            Scale::Log | Scale::Logit => {
+                (-v.min(1. - v).max(MIN_SHOWN).log10()).ceil().max(1.) as usize
+            }
+        };
+        format!("{:.*}", places, v)
+    }
+}
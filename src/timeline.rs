@@ -1,78 +1,427 @@
-use crate::{data::CUM, CurveFn};
+use crate::{util::bisect, CoordFn, InvFn};
 use itertools_num::linspace;
 use plotters::prelude::*;
+use std::cmp::Ordering;
 
-/// Calculates the probability from the portion.
-fn prob_from_portion(mut portion: f32, pts: &[(f32, f32)]) -> Option<f32> {
-    if portion <= 0.0 {
-        portion = 0.00;
-    } else if portion >= 1.0 {
-        portion = 1.0;
+/// How to interpolate between the points of a progress distribution.
+#[derive(Clone, Copy)]
+pub enum Interp {
+    /// Straight lines between the points.
+    Linear,
+    /// Piecewise cubic Hermite interpolation with slopes chosen so that the curve is smooth and
+    /// doesn't overshoot the points, so that it stays monotone (PCHIP).
+    Pchip,
+}
+
+/// Calculates the slope of the PCHIP interpolant at the `i`th point.
+///
+/// Interior slopes are the weighted harmonic mean of the neighbouring secants, or zero at a local
+/// extremum. End slopes use the one-sided three-point formula, limited so as not to overshoot.
+fn pchip_slope(pts: &[(f32, f32)], i: usize) -> f32 {
+    let n = pts.len();
+    let secant = |j: usize| {
+        let (x1, y1) = pts[j];
+        let (x2, y2) = pts[j + 1];
+        (x2 - x1, (y2 - y1) / (x2 - x1))
+    };
+
+    if n < 2 {
+        return 0.;
+    }
+
+    if i == 0 || i == n - 1 {
+        let (h0, d0) = secant(if i == 0 { 0 } else { n - 2 });
+        if n == 2 || h0 <= 0. {
+            return if h0 > 0. { d0 } else { 0. };
+        }
+
+        let (h1, d1) = secant(if i == 0 { 1 } else { n - 3 });
+        if h1 <= 0. {
+            return d0;
+        }
+
+        let d = ((2. * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+        return if d * d0 <= 0. {
+            0.
+        } else if d0 * d1 < 0. && d.abs() > 3. * d0.abs() {
+            3. * d0
+        } else {
+            d
+        };
     }
 
+    let (h0, d0) = secant(i - 1);
+    let (h1, d1) = secant(i);
+    if h0 <= 0. || h1 <= 0. || d0 * d1 <= 0. {
+        return 0.;
+    }
+
+    let w1 = 2. * h1 + h0;
+    let w2 = h1 + 2. * h0;
+    (w1 + w2) / (w1 / d0 + w2 / d1)
+}
+
+/// Calculates the probability from the portion. Portions beyond the first or last point take the
+/// probability at that point.
+///
+/// # Arguments
+///
+/// - `portion: f32` - The portion of the required insights that have been discovered.
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+pub(crate) fn prob_from_portion(portion: f32, pts: &[(f32, f32)], interp: Interp) -> Option<f32> {
+    let portion = portion.clamp(pts.first()?.0, pts.last()?.0);
+
     for i in 0..pts.len() - 1 {
         let (x1, y1) = pts[i];
         let (x2, y2) = pts[i + 1];
 
         if (x1..=x2).contains(&portion) {
-            return Some(y1 + (portion - x1) / (x2 - x1) * (y2 - y1));
+            let h = x2 - x1;
+            if h <= 0. {
+                return Some(y2);
+            }
+
+            let t = (portion - x1) / h;
+            return Some(match interp {
+                Interp::Linear => y1 + t * (y2 - y1),
+                Interp::Pchip => {
+                    let (m1, m2) = (pchip_slope(pts, i), pchip_slope(pts, i + 1));
+                    let (t2, t3) = (t * t, t * t * t);
+
+                    (2. * t3 - 3. * t2 + 1.) * y1
+                        + (t3 - 2. * t2 + t) * h * m1
+                        + (3. * t2 - 2. * t3) * y2
+                        + (t3 - t2) * h * m2
+                }
+            });
         }
     }
 
     None
 }
 
+/// Evaluates the interpolated progress distribution at evenly spaced portions, e.g., for drawing.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `n: usize` - The number of portions to evaluate it at.
+pub(crate) fn resample(pts: &[(f32, f32)], interp: Interp, n: usize) -> Option<Vec<(f32, f32)>> {
+    linspace(0f32, 1f32, n)
+        .map(|x| Some((x, prob_from_portion(x, pts, interp)?)))
+        .collect()
+}
+
+/// Brackets the threshold below which portions' required number of insights is never reached
+/// under the fit curve, as `(lo, hi)` with `lo` never reached and `hi` reached. Returns nothing if
+/// not even the current number of insights is reached.
+///
+/// # Arguments
+///
+/// - `inv_curve: &InvFn` - The inverse of the fit curve.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+fn reach_threshold(inv_curve: &InvFn, insights_cnt: f32) -> Option<(f32, f32)> {
+    let reached = |portion: f32| inv_curve(insights_cnt / portion).year().is_some();
+
+    if !reached(1.) {
+        return None;
+    }
+
+    Some(bisect(|portion| !reached(portion), 0., 1.))
+}
+
+/// Calculates the probability that the required number of insights is never reached under the fit
+/// curve.
+///
+/// Smaller portions require more insights, so the portions whose required count is never reached
+/// are exactly those below some threshold. The threshold is found by bisection, and the
+/// probability is that of the portion lying at or below it.
+///
+/// # Arguments
+///
+/// - `pts: &[(f32, f32)]` - A progress distribution.
+/// - `interp: Interp` - How to interpolate between the points.
+/// - `inv_curve: &InvFn` - The inverse of the fit curve.
+/// - `insights_cnt: f32` - The number of insights discovered so far.
+pub(crate) fn never_prob(
+    pts: &[(f32, f32)],
+    interp: Interp,
+    inv_curve: &InvFn,
+    insights_cnt: f32,
+) -> Option<f32> {
+    match reach_threshold(inv_curve, insights_cnt) {
+        Some((lo, _)) => prob_from_portion(lo, pts, interp),
+        None => prob_from_portion(1., pts, interp),
+    }
+}
+
+/// Ways of showing the timeline.
+#[derive(Clone, Copy)]
+pub enum TimelineView {
+    /// The probability that the required number of insights has been reached by each year.
+    Cumulative,
+    /// The probability that the required number of insights is reached within each period of this
+    /// many years.
+    Density(u32),
+}
+
+/// The smallest portion the timeline covers, i.e., it covers up to `1 / MIN_PORTION` times the
+/// number of insights discovered so far.
+const MIN_PORTION: f32 = 1e-4;
+
+/// The number of log-spaced portions the timeline is evaluated at, besides the points of the
+/// progress distribution.
+const TIMELINE_GRID: usize = 1_000;
+
+/// The quantiles of the timeline marked on the chart.
+const MARKED_QUANTILES: [f32; 5] = [0.1, 0.25, 0.5, 0.75, 0.9];
+
+/// The implied timeline, i.e., the probability that the required number of insights has been
+/// reached by each year, given that it hadn't been reached when the projection starts.
+pub(crate) struct Timeline {
+    /// Years from the start of the projection on, in increasing order, and the probability that
+    /// the required number of insights has been reached by then.
+    pts: Vec<(f32, f32)>,
+    /// The probability that the required number of insights is never reached, which is zero
+    /// unless the fit curve levels off.
+    never: f32,
+}
+
+impl Timeline {
+    /// Computes the timeline implied by a progress distribution and a fit curve.
+    ///
+    /// Each portion maps to the fractional year the fit curve reaches the number of insights it
+    /// requires. The portions are the points of the progress distribution, so its corners map
+    /// exactly, together with a log-spaced grid, since the map bends straight lines, and the
+    /// threshold below which the required number of insights is never reached.
+    ///
+    /// Curves that don't level off reach every count eventually, so for them the threshold only
+    /// marks how far the search for crossings goes, and the portions below it are beyond the end
+    /// of the timeline rather than never reached.
+    ///
+    /// Where the fit curve has already passed a portion's required number of insights by `now`,
+    /// that portion is ruled out, since the projection starts there, and the timeline is
+    /// conditioned on the rest. Returns nothing if every portion is ruled out.
+    ///
+    /// # Arguments
+    ///
+    /// - `pts: &[(f32, f32)]` - A progress distribution.
+    /// - `interp: Interp` - How to interpolate between the points.
+    /// - `inv_curve: &InvFn` - The inverse of the fit curve.
+    /// - `insights_cnt: f32` - The number of insights discovered so far.
+    /// - `saturates: bool` - Whether the fit curve levels off.
+    /// - `now: f32` - The year the projection starts from.
+    pub(crate) fn new(
+        pts: &[(f32, f32)],
+        interp: Interp,
+        inv_curve: &InvFn,
+        insights_cnt: f32,
+        saturates: bool,
+        now: f32,
+    ) -> Option<Timeline> {
+        let never = if saturates {
+            never_prob(pts, interp, inv_curve, insights_cnt)?
+        } else {
+            0.
+        };
+        // If not even the current number of insights is reached, the timeline is empty.
+        let threshold = match reach_threshold(inv_curve, insights_cnt) {
+            Some((_, threshold)) => threshold,
+            None => {
+                return Some(Timeline {
+                    pts: Vec::new(),
+                    never,
+                })
+            }
+        };
+
+        let mut portions: Vec<f32> = linspace(MIN_PORTION.log10(), 0., TIMELINE_GRID)
+            .map(|lp| 10f32.powf(lp))
+            .chain(pts.iter().map(|&(x, _)| x))
+            .chain(std::iter::once(threshold))
+            .filter(|&p| p >= MIN_PORTION.max(threshold) && p <= 1.)
+            .collect();
+        portions.sort_by(|p1, p2| p1.partial_cmp(p2).unwrap_or(Ordering::Equal));
+        portions.dedup();
+
+        let crossings = portions
+            .into_iter()
+            .map(|portion| {
+                let year = inv_curve(insights_cnt / portion).year();
+                Some((year, 1. - prob_from_portion(portion, pts, interp)?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        // Every portion is at least the threshold, so this only drops portions that bisecting
+        // for it misjudged by rounding.
+        let mut pts: Vec<(f32, f32)> = crossings
+            .into_iter()
+            .filter_map(|(year, prob)| Some((year?, prob)))
+            .collect();
+        pts.sort_by(|(y1, p1), (y2, p2)| {
+            y1.partial_cmp(y2)
+                .unwrap_or(Ordering::Equal)
+                .then(p1.partial_cmp(p2).unwrap_or(Ordering::Equal))
+        });
+
+        let before_now = Timeline { pts, never };
+        let ruled_out = before_now.prob_by(now);
+        let rest = 1. - ruled_out;
+        if rest <= 0. {
+            return None;
+        }
+
+        let pts = std::iter::once((now, 0.))
+            .chain(
+                before_now
+                    .pts
+                    .into_iter()
+                    .filter(|&(year, _)| year > now)
+                    .map(|(year, prob)| (year, (prob - ruled_out) / rest)),
+            )
+            .collect();
+
+        Some(Timeline {
+            pts,
+            never: never / rest,
+        })
+    }
+
+    /// Years, in increasing order, and the probability that the required number of insights has
+    /// been reached by then.
+    pub(crate) fn points(&self) -> &[(f32, f32)] {
+        &self.pts
+    }
+
+    /// The probability that the required number of insights is never reached, which is zero
+    /// unless the fit curve levels off.
+    pub(crate) fn never(&self) -> f32 {
+        self.never
+    }
+
+    /// The probability that the required number of insights has been reached by `year`.
+    pub(crate) fn prob_by(&self, year: f32) -> f32 {
+        let i = self.pts.partition_point(|&(y, _)| y <= year);
+
+        if i == 0 {
+            0.
+        } else if i == self.pts.len() {
+            self.pts[i - 1].1
+        } else {
+            let (y1, p1) = self.pts[i - 1];
+            let (y2, p2) = self.pts[i];
+            p1 + (year - y1) / (y2 - y1) * (p2 - p1)
+        }
+    }
+
+    /// The first year by which the required number of insights has been reached with probability
+    /// `q`. Returns nothing if that never happens.
+    pub(crate) fn quantile(&self, q: f32) -> Option<f32> {
+        let i = self.pts.partition_point(|&(_, p)| p < q);
+        let &(y2, p2) = self.pts.get(i)?;
+
+        if i == 0 || p2 <= q {
+            return Some(y2);
+        }
+
+        let (y1, p1) = self.pts[i - 1];
+        Some(y1 + (q - p1) / (p2 - p1) * (y2 - y1))
+    }
+
+    /// The probability that the required number of insights is reached within each period of
+    /// `width` years, as the start of the period and the probability. Periods are aligned to
+    /// multiples of `width` and cover `start` to `end`.
+    pub(crate) fn density(&self, start: i32, end: i32, width: u32) -> Vec<(i32, f32)> {
+        let width = width.max(1) as i32;
+        let first = start.div_euclid(width) * width;
+
+        (first..end)
+            .step_by(width as usize)
+            .map(|year| {
+                let mass = self.prob_by((year + width) as f32) - self.prob_by(year as f32);
+                (year, mass.max(0.))
+            })
+            .collect()
+    }
+
+    /// The probability that the required number of insights is reached, but only after the last
+    /// year of the timeline, e.g., because it requires more insights than the timeline covers.
+    pub(crate) fn beyond(&self) -> f32 {
+        let reached = self.pts.last().map_or(0., |&(_, prob)| prob);
+        (1. - self.never - reached).max(0.)
+    }
+
+    /// The mean year the required number of insights is reached, given that it is reached by the
+    /// last year of the timeline.
+    pub(crate) fn mean(&self) -> Option<f32> {
+        let &(first_year, first_prob) = self.pts.first()?;
+        let total = self.pts.last()?.1;
+        if total <= 0. {
+            return None;
+        }
+
+        let weighted: f32 = self
+            .pts
+            .windows(2)
+            .map(|w| 0.5 * (w[0].0 + w[1].0) * (w[1].1 - w[0].1))
+            .sum();
+
+        Some((first_year * first_prob + weighted) / total)
+    }
+}
+
 /// Draws the projected timeline.
 ///
 /// # Arguments
 ///
+/// - `now: i32` - The year the projection starts from.
 /// - `max_year: i32` - Upper bound for how far to extend the projection.
-/// - `pts: &[(f32, f32)]` - A progress distribution.
-/// - `inv_curve: &Option<CurveFn>` - The inverse of the fit curve.
+/// - `timeline: &Timeline` - The implied timeline.
 ///
 /// # Remark
 ///
 /// The plot may not extend to `max_year` if large enough values cannot be obtained.
 /// It is an upper bound, not a least upper bound.
-pub(crate) fn draw_timeline(
-    max_year: i32,
-    pts: &[(f32, f32)],
-    inv_curve: &Option<CurveFn>,
-) -> Option<()> {
-    let insights_cnt = CUM.last()?.1 as f32;
-
-    let inv_curve = (*inv_curve).as_ref()?;
-
+///
+/// If there is some probability that the required number of insights is never reached, the curve
+/// approaches one minus that probability rather than one, and the ceiling is marked on the plot.
+/// The quantiles in `MARKED_QUANTILES` and the mean are marked too.
+///
+/// Returns the map from pixels on the canvas to coordinates on the chart.
+pub(crate) fn draw_timeline(now: i32, max_year: i32, timeline: &Timeline) -> Option<CoordFn> {
     let backend = CanvasBackend::new("timeline_plot")?;
     let root = backend.into_drawing_area();
     let font: FontDesc = ("Arial", 20.0).into();
 
     root.fill(&WHITE).ok()?;
 
-    let npts: Vec<(i32, f32)> = linspace(0.01, 0.999, 10_000)
-        .map(|portion| {
-            (
-                inv_curve((insights_cnt / portion) as i32) as i32,
-                1. - prob_from_portion(portion, pts).unwrap() as f32,
-            )
-        })
-        .filter(|(y, _)| *y >= 2020)
-        .rev()
+    let never = timeline.never();
+
+    let now = now as f32;
+
+    let npts: Vec<(f32, f32)> = timeline
+        .pts
+        .iter()
+        .copied()
+        .filter(|&(y, _)| y >= now)
         .collect();
 
-    let last_year = npts.last()?.0;
+    let last_year = npts.last().map_or(max_year as f32, |&(year, _)| year);
 
-    let max_year = max_year.min(last_year);
+    let max_year = (max_year as f32).min(last_year);
 
     let mut chart = ChartBuilder::on(&root)
         .caption("Implied Timeline", font)
         .x_label_area_size(50)
         .y_label_area_size(50)
-        .build_ranged(2020..max_year, 0.01f32..1f32)
+        .build_ranged(now..max_year, 0.01f32..1f32)
         .ok()?;
 
     chart
         .configure_mesh()
+        .x_label_formatter(&|year| format!("{:.0}", year))
         .x_desc("Max Year")
         .y_desc("Probability")
         .draw()
@@ -82,5 +431,172 @@ pub(crate) fn draw_timeline(
         .draw_series(LineSeries::new(npts.into_iter(), &RGBColor(0, 136, 238)))
         .ok()?;
 
-    Some(())
+    for &q in MARKED_QUANTILES.iter() {
+        let year = match timeline.quantile(q) {
+            Some(year) if (now..=max_year).contains(&year) => year,
+            _ => continue,
+        };
+
+        let (label, color) = if q == 0.5 {
+            (format!("Median: {:.0}", year), RGBColor(0, 153, 68))
+        } else {
+            (
+                format!("{:.0}%: {:.0}", 100. * q, year),
+                RGBColor(160, 160, 160),
+            )
+        };
+
+        chart
+            .draw_series(LineSeries::new(vec![(year, 0.01), (year, 1.)], &color))
+            .ok()?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &color));
+    }
+
+    if let Some(year) = timeline.mean() {
+        // The mean only covers what is reached within the timeline.
+        let label = if timeline.beyond() > 1e-3 {
+            format!("Mean (if reached by {:.0}): {:.0}", last_year, year)
+        } else {
+            format!("Mean (if reached): {:.0}", year)
+        };
+
+        if (now..=max_year).contains(&year) {
+            chart
+                .draw_series(LineSeries::new(
+                    vec![(year, 0.01), (year, 1.)],
+                    &RGBColor(153, 51, 204),
+                ))
+                .ok()?
+                .label(label)
+                .legend(|(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], &RGBColor(153, 51, 204))
+                });
+        }
+    }
+
+    if never > 0. {
+        chart
+            .draw_series(LineSeries::new(
+                vec![(now, 1. - never), (max_year, 1. - never)],
+                &RGBColor(238, 85, 0),
+            ))
+            .ok()?
+            .label(format!("Pr(never) = {:.1}%", 100. * never))
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RGBColor(238, 85, 0)));
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::LowerRight)
+        .background_style(&WHITE.mix(0.8))
+        .border_style(&BLACK)
+        .draw()
+        .ok()?;
+
+    Some(Box::new(chart.into_coord_trans()))
+}
+
+/// Draws the projected timeline as the probability that the required number of insights is
+/// reached within each period of `width` years.
+///
+/// # Arguments
+///
+/// - `now: i32` - The year the projection starts from.
+/// - `max_year: i32` - Upper bound for how far to extend the projection.
+/// - `timeline: &Timeline` - The implied timeline.
+/// - `width: u32` - The number of years in each period.
+///
+/// Returns the map from pixels on the canvas to coordinates on the chart.
+pub(crate) fn draw_timeline_density(
+    now: i32,
+    max_year: i32,
+    timeline: &Timeline,
+    width: u32,
+) -> Option<CoordFn> {
+    let backend = CanvasBackend::new("timeline_plot")?;
+    let root = backend.into_drawing_area();
+    let font: FontDesc = ("Arial", 20.0).into();
+
+    root.fill(&WHITE).ok()?;
+
+    let last_year = timeline
+        .pts
+        .last()
+        .map_or(max_year, |&(year, _)| year as i32);
+    let max_year = max_year.min(last_year);
+    if max_year <= now {
+        return None;
+    }
+
+    let bins = timeline.density(now, max_year, width);
+    let max_mass = bins.iter().map(|&(_, mass)| mass).fold(0f32, f32::max);
+    let first_year = bins.first()?.0;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Implied Timeline", font)
+        .x_label_area_size(50)
+        .y_label_area_size(50)
+        .build_ranged(first_year..max_year, 0f32..(1.1 * max_mass).max(0.001))
+        .ok()?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Year")
+        .y_desc(if width == 1 {
+            "Probability per year".to_string()
+        } else {
+            format!("Probability per {} years", width)
+        })
+        .draw()
+        .ok()?;
+
+    let width = width.max(1) as i32;
+    chart
+        .draw_series(bins.iter().map(|&(year, mass)| {
+            Rectangle::new(
+                [(year, 0.), ((year + width).min(max_year), mass)],
+                RGBColor(0, 136, 238).mix(0.6).filled(),
+            )
+        }))
+        .ok()?;
+
+    let trans = chart.into_coord_trans();
+    Some(Box::new(move |pixel| {
+        trans(pixel).map(|(year, prob)| (year as f32, prob))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pchip_is_monotone_and_interpolates() {
+        // Flat stretches and steep steps are where cubic splines overshoot.
+        let pts = vec![
+            (0., 0.),
+            (0.1, 0.02),
+            (0.2, 0.02),
+            (0.25, 0.6),
+            (0.6, 0.65),
+            (0.9, 0.99),
+            (1., 1.),
+        ];
+
+        for &(x, y) in &pts {
+            let prob = prob_from_portion(x, &pts, Interp::Pchip).unwrap();
+            assert!((prob - y).abs() < 1e-6, "{} at {}", prob, x);
+        }
+
+        let probs: Vec<f32> = linspace(0f32, 1f32, 1001)
+            .map(|x| prob_from_portion(x, &pts, Interp::Pchip).unwrap())
+            .collect();
+        assert!(probs.windows(2).all(|w| w[1] >= w[0] - 1e-6));
+        assert!(probs.iter().all(|&p| (-1e-6..=1. + 1e-6).contains(&p)));
+
+        // The flat stretch stays flat.
+        let flat = prob_from_portion(0.15, &pts, Interp::Pchip).unwrap();
+        assert!((flat - 0.02).abs() < 1e-6);
+    }
 }
@@ -1,4 +1,348 @@
 use differential_evolution2::self_adaptive_de;
+use std::f64::consts::{PI, SQRT_2};
+
+/// How far (in years) to search for a crossing before concluding that a curve never reaches a
+/// given count.
+const MAX_SPAN: f32 = 100_000.;
+
+/// When a fit curve reaches a given number of insights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crossing {
+    /// The curve reaches the count in the given (fractional) year.
+    Year(f32),
+    /// The curve never reaches the count.
+    Never,
+}
+
+impl Crossing {
+    /// The year of the crossing, if there is one.
+    pub fn year(self) -> Option<f32> {
+        match self {
+            Crossing::Year(year) => Some(year),
+            Crossing::Never => None,
+        }
+    }
+}
+
+/// Numerically inverts a monotone curve using bracketed bisection over years.
+///
+/// Starting at `start`, the search moves towards `target` with doubling step sizes until the
+/// crossing is bracketed, and then bisects. If no crossing is found within `MAX_SPAN` years, the
+/// curve is taken to never reach `target`.
+///
+/// # Arguments
+///
+/// - `curve: F` - The curve to invert, mapping years to cumulative insight counts.
+/// - `target: f32` - The insight count to find the year for.
+/// - `start: f32` - The year to start the search from.
+pub(crate) fn invert<F: Fn(f32) -> f32>(curve: F, target: f32, start: f32) -> Crossing {
+    let gap = |year: f32| curve(year) - target;
+
+    let gap_start = gap(start);
+    if gap_start == 0. {
+        return Crossing::Year(start);
+    } else if gap_start.is_nan() {
+        return Crossing::Never;
+    }
+
+    // Move forward in time if the curve is heading towards the target, backwards otherwise.
+    let increasing = curve(start + 1.) >= curve(start);
+    let dir = if (gap_start < 0.) == increasing {
+        1.
+    } else {
+        -1.
+    };
+
+    // `lo` always lies on the same side of the target as `start`, `hi` on the other.
+    let mut lo;
+    let mut hi = start;
+    let mut step = 1.;
+    loop {
+        if step > MAX_SPAN {
+            return Crossing::Never;
+        }
+
+        lo = hi;
+        hi = start + dir * step;
+
+        // NaNs compare false, so they are never mistaken for a crossing.
+        if gap(hi) * gap_start <= 0. {
+            break;
+        }
+
+        step *= 2.;
+    }
+
+    for _ in 0..64 {
+        let mid = 0.5 * (lo + hi);
+        if mid == lo || mid == hi {
+            break;
+        }
+
+        if gap(mid) * gap_start > 0. {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    Crossing::Year(0.5 * (lo + hi))
+}
+
+/// Bisects for the boundary between the values in `lo..hi` that lie below it, i.e., for which
+/// `below` holds, and those that don't. Returns the final bracket `(lo, hi)`, with `lo` below the
+/// boundary and `hi` not, assuming that this holds of the initial one.
+///
+/// # Arguments
+///
+/// - `below: F` - Whether a value lies below the boundary.
+/// - `lo: f32` - A value below the boundary.
+/// - `hi: f32` - A value that isn't below the boundary.
+pub(crate) fn bisect<F: Fn(f32) -> bool>(below: F, mut lo: f32, mut hi: f32) -> (f32, f32) {
+    for _ in 0..40 {
+        let mid = 0.5 * (lo + hi);
+        if below(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    (lo, hi)
+}
+
+/// Computes the natural logarithm of the gamma function using the Lanczos approximation.
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.;
+    const COEFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // reflection formula
+        (PI / (PI * x).sin()).ln() - ln_gamma(1. - x)
+    } else {
+        let x = x - 1.;
+        let t = x + G + 0.5;
+        let series: f64 = COEFS[1..]
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c / (x + i as f64 + 1.))
+            .sum();
+
+        0.5 * (2. * PI).ln() + (x + 0.5) * t.ln() - t + (COEFS[0] + series).ln()
+    }
+}
+
+/// Computes the complementary error function, with fractional error below 1.2e-7.
+pub(crate) fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+
+    let res = t * poly.exp();
+    if x >= 0. {
+        res
+    } else {
+        2. - res
+    }
+}
+
+/// Computes the probability that a standard normal exceeds `z`.
+pub(crate) fn normal_sf(z: f64) -> f64 {
+    0.5 * erfc(z / SQRT_2)
+}
+
+/// Computes the quantile function of the standard normal using Acklam's rational approximation.
+pub(crate) fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    // rational approximation in the tails
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    };
+
+    if p <= 0. {
+        f64::NEG_INFINITY
+    } else if p >= 1. {
+        f64::INFINITY
+    } else if p < P_LOW {
+        tail((-2. * p.ln()).sqrt())
+    } else if p > 1. - P_LOW {
+        -tail((-2. * (1. - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    }
+}
+
+/// Computes the upper regularized incomplete gamma function `Q(a, x)`, using its series below
+/// `a + 1` and its continued fraction above.
+pub(crate) fn gamma_inc_upper(a: f64, x: f64) -> f64 {
+    const EPS: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0. {
+        return 1.;
+    }
+
+    let front = (a * x.ln() - x - ln_gamma(a)).exp();
+
+    if x < a + 1. {
+        let mut term = 1. / a;
+        let mut sum = term;
+        for n in 1..500 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPS {
+                break;
+            }
+        }
+
+        1. - front * sum
+    } else {
+        // Lentz's method
+        let mut b = x + 1. - a;
+        let mut c = 1. / TINY;
+        let mut d = 1. / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1. / d;
+            h *= d * c;
+            if (d * c - 1.).abs() < EPS {
+                break;
+            }
+        }
+
+        front * h
+    }
+}
+
+/// Computes the regularized incomplete beta function `I_x(a, b)`.
+pub(crate) fn beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    } else if x >= 1. {
+        return 1.;
+    }
+
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+
+    // The continued fraction converges quickly below this point, and symmetry handles the rest.
+    if x < (a + 1.) / (a + b + 2.) {
+        front * beta_cf(a, b, x) / a
+    } else {
+        1. - front * beta_cf(b, a, 1. - x) / b
+    }
+}
+
+/// Evaluates the continued fraction for the incomplete beta function using Lentz's method.
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    const EPS: f64 = 1e-14;
+
+    // Keeps the denominators away from zero.
+    let guard = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.;
+    let mut d = 1. / guard(1. - (a + b) * x / (a + 1.));
+    let mut h = d;
+
+    for m in 1..300 {
+        let m = m as f64;
+
+        let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        d = 1. / guard(1. + even * d);
+        c = guard(1. + even / c);
+        h *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        d = 1. / guard(1. + odd * d);
+        c = guard(1. + odd / c);
+        h *= d * c;
+
+        if (d * c - 1.).abs() < EPS {
+            break;
+        }
+    }
+
+    h
+}
+
+/// Computes the inverse of the regularized incomplete beta function by bisection, i.e., the
+/// quantile function of a Beta distribution.
+pub(crate) fn beta_inc_inv(a: f64, b: f64, p: f64) -> f64 {
+    let (mut lo, mut hi) = (0., 1.);
+    for _ in 0..60 {
+        let mid = 0.5 * (lo + hi);
+        if beta_inc(a, b, mid) < p {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    0.5 * (lo + hi)
+}
 
 /// Computes a sigmoid.
 pub(crate) fn sigmoid(m: f32, k: f32, b: f32, x: f32) -> f32 {
@@ -46,3 +390,161 @@ pub(crate) fn fit_sigmoid(pts: &[(f32, f32)]) -> Option<(f32, f32, f32)> {
 //        }
 //     };
 //}
+
+/// The closest non-decreasing sequence to `ys` in the least squares sense, found with the pool
+/// adjacent violators algorithm.
+pub(crate) fn isotonic(ys: &[f32]) -> Vec<f32> {
+    // Runs of equal values, as (sum, count).
+    let mut blocks: Vec<(f32, usize)> = Vec::new();
+
+    for &y in ys {
+        blocks.push((y, 1));
+
+        while blocks.len() > 1 {
+            let (s2, n2) = blocks[blocks.len() - 1];
+            let (s1, n1) = blocks[blocks.len() - 2];
+            if s1 / n1 as f32 <= s2 / n2 as f32 {
+                break;
+            }
+
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (s1 + s2, n1 + n2);
+        }
+    }
+
+    blocks
+        .into_iter()
+        .flat_map(|(s, n)| vec![s / n as f32; n])
+        .collect()
+}
+
+/// Moving average of `ys` over windows of `2 * radius + 1` values, truncated at the ends. This
+/// keeps non-decreasing sequences non-decreasing.
+pub(crate) fn moving_average(ys: &[f32], radius: usize) -> Vec<f32> {
+    (0..ys.len())
+        .map(|i| {
+            let window = &ys[i.saturating_sub(radius)..(i + radius + 1).min(ys.len())];
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tol: f64) {
+        assert!(
+            (actual - expected).abs() <= tol,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn invert_round_trips_exponential() {
+        let curve = |year: f32| 10. * (0.05 * (year - 2000.)).exp();
+
+        // Targets both ahead of and behind the start of the search.
+        for &target in [20., 100., 1_000., 1e6, 5.].iter() {
+            let year = invert(curve, target, 2016.).year().unwrap();
+            assert!(
+                (curve(year) / target - 1.).abs() < 1e-4,
+                "{} at {}",
+                curve(year),
+                year
+            );
+        }
+    }
+
+    #[test]
+    fn invert_never_reaches_above_sigmoid_ceiling() {
+        let curve = |year: f32| sigmoid(300., 0.05, 2000., year);
+
+        assert_eq!(invert(curve, 500., 2016.), Crossing::Never);
+        let year = invert(curve, 250., 2016.).year().unwrap();
+        assert!((curve(year) - 250.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn invert_flat_curve() {
+        let curve = |_: f32| 100.;
+
+        assert_eq!(invert(curve, 200., 2016.), Crossing::Never);
+        assert_eq!(invert(curve, 50., 2016.), Crossing::Never);
+        assert_eq!(invert(curve, 100., 2016.), Crossing::Year(2016.));
+    }
+
+    #[test]
+    fn ln_gamma_known_values() {
+        assert_close(ln_gamma(1.), 0., 1e-10);
+        assert_close(ln_gamma(5.), 24f64.ln(), 1e-10);
+        assert_close(ln_gamma(0.5), PI.sqrt().ln(), 1e-10);
+        assert_close(ln_gamma(0.1), 2.252_712_651_734_206, 1e-10);
+    }
+
+    #[test]
+    fn normal_known_values() {
+        assert_close(normal_quantile(0.5), 0., 1e-8);
+        assert_close(normal_quantile(0.975), 1.959_963_985, 1e-6);
+        assert_close(normal_quantile(0.01), -2.326_347_874, 1e-6);
+        assert_close(normal_sf(0.), 0.5, 1e-7);
+        assert_close(normal_sf(1.959_963_985), 0.025, 1e-7);
+        assert_close(normal_sf(-1.), 0.841_344_746, 1e-7);
+    }
+
+    #[test]
+    fn gamma_inc_upper_known_values() {
+        // Q(1, x) = exp(-x), and Q(3, x) = exp(-x) (1 + x + x^2 / 2).
+        for &x in [0.1, 1., 2., 10.].iter() {
+            assert_close(gamma_inc_upper(1., x), (-x).exp(), 1e-10);
+            assert_close(
+                gamma_inc_upper(3., x),
+                (-x).exp() * (1. + x + x * x / 2.),
+                1e-10,
+            );
+        }
+    }
+
+    #[test]
+    fn beta_inc_known_values() {
+        // I_x(1, 1) = x, and I_0.4(2, 3) sums binomial probabilities.
+        assert_close(beta_inc(1., 1., 0.3), 0.3, 1e-10);
+        assert_close(beta_inc(2., 3., 0.4), 0.5248, 1e-10);
+        assert_close(beta_inc(0.5, 0.5, 0.5), 0.5, 1e-10);
+    }
+
+    #[test]
+    fn beta_inc_inv_round_trips() {
+        for &(a, b) in [(0.5, 0.5), (1., 1.), (2., 5.), (10., 3.)].iter() {
+            for &p in [0.01, 0.25, 0.5, 0.9, 0.999].iter() {
+                assert_close(beta_inc(a, b, beta_inc_inv(a, b, p)), p, 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn isotonic_pools_violators() {
+        assert_eq!(isotonic(&[]), Vec::<f32>::new());
+        assert_eq!(isotonic(&[0.1, 0.2, 0.3]), vec![0.1, 0.2, 0.3]);
+        assert_eq!(isotonic(&[0.1, 0.5, 0.3, 0.7]), vec![0.1, 0.4, 0.4, 0.7]);
+        assert_eq!(isotonic(&[0.6, 0.4, 0.2]), vec![0.4, 0.4, 0.4]);
+
+        let fit = isotonic(&[0.3, 0.1, 0.8, 0.2, 0.4, 0.9, 0.5]);
+        assert!(fit.windows(2).all(|w| w[0] <= w[1]));
+        // Pooling preserves the sum.
+        assert!((fit.iter().sum::<f32>() - 3.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn moving_average_keeps_monotone() {
+        assert_eq!(moving_average(&[1., 2., 3.], 0), vec![1., 2., 3.]);
+        assert_eq!(moving_average(&[0., 3., 6., 9.], 1), vec![1.5, 3., 6., 7.5]);
+
+        let ys = [0., 0., 0.1, 0.5, 0.5, 0.9, 1.];
+        let smooth = moving_average(&ys, 2);
+        assert_eq!(smooth.len(), ys.len());
+        assert!(smooth.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -3,60 +3,383 @@ import * as wasm from "./pkg/insights2.js";
 wasm.default("./pkg/insights2_bg.wasm").then(function() {
   const state = wasm.State.new();
   state.draw_dist();
+  state.draw_required();
   state.draw_insights();
-  state.draw_timeline();
+
+  // Converts the position of a mouse event to canvas pixels, which differ from CSS pixels when
+  // the canvas is displayed at a different size from its resolution.
+  function canvas_pixel(canvas, e) {
+    const rect = canvas.getBoundingClientRect();
+    return [
+      (e.clientX - rect.left) * canvas.width / rect.width,
+      (e.clientY - rect.top) * canvas.height / rect.height,
+    ];
+  }
+
+  // Timeline
+  const never_prob = document.getElementById('never_prob');
+  const timeline_stats = document.getElementById('timeline_stats');
+  const by_year = document.getElementById('by_year');
+  const prob_by = document.getElementById('prob_by');
+
+  function draw_timeline() {
+    state.draw_timeline();
+
+    const flat = state.timeline_points();
+    if (flat === undefined) {
+      never_prob.innerText = "";
+      timeline_stats.innerText = "The timeline couldn't be computed for this curve.";
+      prob_by.innerText = "";
+      return;
+    }
+
+    const never = state.never_prob();
+    never_prob.innerText = never === undefined ? "" : `Pr(never) = ${(100 * never).toFixed(1)}%`;
+
+    // Quantiles past the end of the timeline are either never reached, or reached later on.
+    const end = flat.length > 0 ? flat[flat.length - 2].toFixed(0) : "now";
+    const year = q => {
+      const y = state.timeline_quantile(q);
+      if (y !== undefined) {
+        return y.toFixed(0);
+      }
+      return never !== undefined && q > 1 - never ? "never" : `after ${end}`;
+    };
+    const mean = state.timeline_mean();
+    const mean_if = state.timeline_beyond() > 1e-3 ? `if reached by ${end}` : "if reached";
+    timeline_stats.innerText =
+      `If not reached by ${now.value}: median ${year(0.5)}, 50% interval: ${year(0.25)} - ${year(0.75)}, ` +
+      `80% interval: ${year(0.1)} - ${year(0.9)}, ` +
+      `mean (${mean_if}): ${mean === undefined ? "-" : mean.toFixed(0)}`;
+
+    const by = state.prob_by(by_year.value);
+    prob_by.innerText = `${(100 * by).toFixed(1)}%`;
+  }
+
+  by_year.addEventListener("change", draw_timeline);
+
+  // The projection starts from the current year unless changed.
+  const now = document.getElementById('now');
+  let now_year = new Date().getFullYear();
+  now.value = now_year;
+  state.set_now(now_year);
+  by_year.min = now_year;
+  now.addEventListener("change", function() {
+    if (!state.set_now(now.value)) {
+      now.value = now_year;
+      return;
+    }
+    now_year = now.value;
+    by_year.min = now_year;
+    draw_timeline();
+  });
+
+  const insights_cnt = document.getElementById('insights_cnt');
+  insights_cnt.addEventListener("change", function() {
+    state.set_insights_cnt(insights_cnt.value);
+    dist_updated();
+  });
+
+  const timeline_view = document.getElementById('timeline_view');
+  timeline_view.addEventListener("change", function() {
+    state.set_timeline_view(timeline_view.value);
+    draw_timeline();
+  });
+
+  draw_timeline();
+
+  const timeline_plot = document.getElementById('timeline_plot');
+  const timeline_cursor = document.getElementById('timeline_cursor');
+  timeline_plot.addEventListener("mousemove", function(e) {
+    const [x, y] = canvas_pixel(timeline_plot, e);
+    const coords = state.chart_coords("Timeline", x, y);
+    timeline_cursor.innerText = coords === undefined ? "" :
+      `${coords[0].toFixed(0)}: ${(100 * coords[1]).toFixed(1)}%`;
+  });
 
   // Progress distribution
   const prog_dist = document.getElementById('progress_dist');
+  const points = document.getElementById('points');
+  const points_error = document.getElementById('points_error');
+  const acceptance = document.getElementById('acceptance');
+  const mixture = document.getElementById('mixture');
+
+  // Hand edits also clear the mixture of pre-set priors, so it is shown after every update.
+  function dist_updated() {
+    state.draw_dist();
+    state.draw_required();
+    draw_timeline();
+
+    mixture.innerText = state.mixture_repr();
+
+    const rate = state.acceptance_rate();
+    acceptance.innerText = rate === undefined ? "" :
+      `Naive rejection sampling would have kept ${(100 * rate).toPrecision(3)}% of samples.`;
+
+    const flat = state.points();
+    const lines = [];
+    for (let i = 0; i < flat.length; i += 2) {
+      lines.push(`${flat[i].toFixed(3)} ${flat[i + 1].toFixed(3)}`);
+    }
+    points.value = lines.join("\n");
+    points_error.innerText = "";
+  }
+
+  document.getElementById('set_points_btn').addEventListener("click", function () {
+    const pairs = points.value
+      .split("\n")
+      .map(line => line.trim().split(/\s+/).map(Number))
+      .filter(pair => pair.length == 2 && !pair.some(isNaN));
+
+    const xs = new Float32Array(pairs.map(pair => pair[0]));
+    const ys = new Float32Array(pairs.map(pair => pair[1]));
+
+    if (state.set_points(xs, ys)) {
+      dist_updated();
+    } else {
+      points_error.innerText = "Points must lie between 0 and 1 and be increasing.";
+    }
+  });
 
   const num_samples = document.getElementById('num_samples');
   num_samples.addEventListener("change", function() {
     state.set_num_samples(num_samples.value);
   });
 
+  const interp = document.getElementById('interp');
+  interp.addEventListener("change", function() {
+    state.set_interp(interp.value);
+    dist_updated();
+  });
+
+  const x_scale = document.getElementById('x_scale');
+  const y_scale = document.getElementById('y_scale');
+  function set_dist_scales() {
+    state.set_dist_scales(x_scale.value, y_scale.value);
+    dist_updated();
+  }
+  x_scale.addEventListener("change", set_dist_scales);
+  y_scale.addEventListener("change", set_dist_scales);
+
+  const sample_method = document.getElementById('sample_method');
+  sample_method.addEventListener("change", function() {
+    state.set_sample_method(sample_method.value);
+  });
+
+  const target_se = document.getElementById('target_se');
+  target_se.addEventListener("change", function() {
+    state.set_target_se(target_se.value);
+  });
+
+  const seed = document.getElementById('seed');
+  seed.addEventListener("change", function() {
+    state.set_seed(seed.value);
+  });
+
+  const n_seeds = document.getElementById('n_seeds');
+  const seed_spread = document.getElementById('seed_spread');
+  document.getElementById('seed_spread_btn').addEventListener("click", function() {
+    const spread = state.seed_spread(n_seeds.value);
+    seed_spread.innerText = spread === undefined ? "Choose a pre-set prior first." :
+      `Largest standard deviation across seeds: ${spread.toFixed(4)}`;
+  });
+
+  const exact = document.getElementById('exact');
+  exact.addEventListener("change", function() {
+    state.set_exact(exact.checked);
+  });
+
   const reset_btn = document.getElementById('reset');
   const pareto_btn = document.getElementById('pareto_btn');
   const pareto_uni_btn = document.getElementById('uniform_pareto_btn');
   const pareto_beta_btn = document.getElementById('beta_pareto_btn');
 
   const min_insights = document.getElementById('min_insights');
+  const min_upper = document.getElementById('min_upper');
+  min_upper.addEventListener("change", function() {
+    state.set_min_upper(min_upper.value);
+  });
+
   const pareto_q = document.getElementById('pareto_q');
   const alpha = document.getElementById('alpha');
   const beta = document.getElementById('beta');
 
+  const mix_weight = document.getElementById('mix_weight');
+  mix_weight.addEventListener("change", function() {
+    state.set_mix_weight(mix_weight.value);
+  });
+
+  const component = document.getElementById('component');
+  document.getElementById('remove_component_btn').addEventListener("click", function () {
+    state.remove_component(component.value - 1);
+    dist_updated();
+  });
+
   pareto_btn.addEventListener("click", function () {
     state.set_pareto(min_insights.value, pareto_q.value);
-    state.draw_dist();
-    state.draw_timeline();
+    dist_updated();
   });
 
   pareto_uni_btn.addEventListener("click", function () {
-    state.set_pareto_uniform(min_insights.value)
-    state.draw_dist();
-    state.draw_timeline();
+    state.set_pareto_uniform(min_insights.value);
+    dist_updated();
   });
 
   pareto_beta_btn.addEventListener("click", function () {
     state.set_pareto_beta(min_insights.value, alpha.value, beta.value);
-    state.draw_dist();
-    state.draw_timeline();
+    dist_updated();
+  });
+
+  const lognormal_median = document.getElementById('lognormal_median');
+  const lognormal_sigma = document.getElementById('lognormal_sigma');
+  document.getElementById('lognormal_btn').addEventListener("click", function () {
+    state.set_lognormal(lognormal_median.value, lognormal_sigma.value);
+    dist_updated();
+  });
+
+  const max_insights = document.getElementById('max_insights');
+  document.getElementById('log_uniform_btn').addEventListener("click", function () {
+    state.set_log_uniform(min_insights.value, max_insights.value);
+    dist_updated();
+  });
+
+  const gamma_shape = document.getElementById('gamma_shape');
+  const gamma_scale = document.getElementById('gamma_scale');
+  document.getElementById('gamma_btn').addEventListener("click", function () {
+    state.set_gamma(gamma_shape.value, gamma_scale.value);
+    dist_updated();
+  });
+
+  const gen_pareto_shape = document.getElementById('gen_pareto_shape');
+  const gen_pareto_scale = document.getElementById('gen_pareto_scale');
+  document.getElementById('gen_pareto_btn').addEventListener("click", function () {
+    state.set_gen_pareto(min_insights.value, gen_pareto_shape.value, gen_pareto_scale.value);
+    dist_updated();
+  });
+
+  const quantiles = document.getElementById('quantiles');
+  const fit_family = document.getElementById('fit_family');
+  const fit_result = document.getElementById('fit_result');
+
+  document.getElementById('fit_quantiles_btn').addEventListener("click", function () {
+    const pairs = quantiles.value
+      .split("\n")
+      .map(line => line.trim().split(/\s+/).map(Number))
+      .filter(pair => pair.length == 2 && !pair.some(isNaN));
+
+    const portions = new Float32Array(pairs.map(pair => pair[0]));
+    const probs = new Float32Array(pairs.map(pair => pair[1]));
+
+    const fit = state.fit_quantiles(portions, probs, fit_family.value);
+    fit_result.innerText = fit === undefined ? "Invalid quantiles." : fit;
+    dist_updated();
+  });
+
+  const fit_replace = document.getElementById('fit_replace');
+  document.getElementById('fit_points_btn').addEventListener("click", function () {
+    const fit = state.fit_points(fit_family.value, fit_replace.checked);
+    fit_result.innerText = fit === undefined ? "Couldn't fit the current distribution." : fit;
+    dist_updated();
   });
 
   reset_btn.addEventListener("click", function () {
     state.reset_progress();
+    dist_updated();
   });
 
   prog_dist.addEventListener("click", add_point);
 
   function add_point(e) {
-    let x = e.offsetX;
-    let y = e.offsetY;
+    // The click that ends dragging a point or a stroke shouldn't also add one.
+    if (dragging || freehand.checked) {
+      dragging = false;
+      return;
+    }
+
+    const [x, y] = canvas_pixel(prog_dist, e);
 
     state.add_point(x, y);
-    state.draw_dist();
-    state.draw_timeline();
+    dist_updated();
   }
 
+  // Points can be dragged, and right-clicking deletes the nearest one.
+  let dragged;
+  let dragging = false;
+
+  // In freehand mode, pressing and dragging sketches a curve instead.
+  const freehand = document.getElementById('freehand');
+  const smooth_stroke = document.getElementById('smooth_stroke');
+  const prog_ctx = prog_dist.getContext('2d');
+  let stroking = false;
+
+  prog_dist.addEventListener("mousedown", function (e) {
+    const [x, y] = canvas_pixel(prog_dist, e);
+    dragging = false;
+
+    if (freehand.checked) {
+      state.begin_stroke(x, y);
+      stroking = true;
+      prog_ctx.strokeStyle = "rgb(238, 85, 0)";
+      prog_ctx.beginPath();
+      prog_ctx.moveTo(x, y);
+    } else {
+      dragged = state.nearest_point(x, y, 0.03);
+    }
+  });
+
+  prog_dist.addEventListener("mousemove", function (e) {
+    if (stroking) {
+      const [x, y] = canvas_pixel(prog_dist, e);
+      state.extend_stroke(x, y);
+      prog_ctx.lineTo(x, y);
+      prog_ctx.stroke();
+      return;
+    }
+
+    if (dragged === undefined) {
+      return;
+    }
+
+    const [x, y] = canvas_pixel(prog_dist, e);
+    const moved = state.move_point(dragged, x, y);
+    if (moved !== undefined) {
+      dragged = moved;
+      dragging = true;
+      state.draw_dist();
+    }
+  });
+
+  window.addEventListener("mouseup", function () {
+    if (stroking) {
+      stroking = false;
+      state.end_stroke(smooth_stroke.checked);
+      dist_updated();
+    }
+
+    if (dragging) {
+      state.end_edit();
+      dist_updated();
+    }
+    dragged = undefined;
+  });
+
+  prog_dist.addEventListener("contextmenu", function (e) {
+    e.preventDefault();
+
+    const [x, y] = canvas_pixel(prog_dist, e);
+    if (state.delete_nearest(x, y)) {
+      dist_updated();
+    }
+  });
+
+  const required_dist = document.getElementById('required_dist');
+  required_dist.addEventListener("click", function (e) {
+    const [x, y] = canvas_pixel(required_dist, e);
+
+    state.add_required_point(x, y);
+    dist_updated();
+  });
+
   // Insights 
   const year_slider = document.getElementById('year_slider');
   const range = document.getElementById('year_range');
@@ -65,7 +388,7 @@ wasm.default("./pkg/insights2_bg.wasm").then(function() {
   reg.addEventListener("change", function() {
     state.set_mode(reg.value);
     state.draw_insights();
-    state.draw_timeline();
+    draw_timeline();
   });
 
   noUiSlider.create(year_slider, {
@@ -80,6 +403,9 @@ wasm.default("./pkg/insights2_bg.wasm").then(function() {
   });
 
   year_slider.noUiSlider.on('update', update_insights);
+  year_slider.noUiSlider.on('end', function() {
+    state.end_edit();
+  });
 
   function update_insights() {
     let [start, end] = year_slider.noUiSlider.get();
@@ -89,7 +415,7 @@ wasm.default("./pkg/insights2_bg.wasm").then(function() {
 
     state.set_year_range(start, end);
     state.draw_insights();
-    state.draw_timeline();
+    draw_timeline();
   }
 
   const last = document.getElementById("last_year")
@@ -105,7 +431,39 @@ wasm.default("./pkg/insights2_bg.wasm").then(function() {
   last.noUiSlider.on('update', function() {
     let val = last.noUiSlider.get();
     state.set_last(val);
-    state.draw_timeline();
+    draw_timeline();
+  });
+
+  // History
+  function restored() {
+    year_slider.noUiSlider.set(Array.from(state.year_range()));
+    reg.value = state.mode();
+    insights_cnt.value = state.insights_cnt();
+    state.draw_insights();
+    dist_updated();
+  }
+
+  document.getElementById('undo').addEventListener("click", function() {
+    if (state.undo()) {
+      restored();
+    }
+  });
+
+  document.getElementById('redo').addEventListener("click", function() {
+    if (state.redo()) {
+      restored();
+    }
+  });
+
+  document.addEventListener("keydown", function(e) {
+    if (!(e.ctrlKey || e.metaKey) || e.key.toLowerCase() != "z" || e.target.tagName == "TEXTAREA") {
+      return;
+    }
+
+    e.preventDefault();
+    if (e.shiftKey ? state.redo() : state.undo()) {
+      restored();
+    }
   });
 
 